@@ -0,0 +1,489 @@
+//! 本地管理接口
+//!
+//! 以 HTTP 与 WebSocket 暴露与桌面端相同的命令，供运维脚本与监控在没有窗口时使用：
+//!
+//! - `POST /api/<命令名>`：请求体为命令参数（JSON 对象，可省略），返回 `{"ok": true, "data": ...}`
+//! - `GET /ws`：发送 `{"command": "...", "args": {...}, "echo": ...}` 调用命令，
+//!   调用 `subscribe_logs` 后服务端持续推送 `{"type": "log", "data": <日志>}`，
+//!   客户端读取跟不上时丢弃日志帧
+//!
+//! 所有请求都需要携带 `Authorization: Bearer <token>` 请求头或 `access_token` 查询参数。
+
+use crate::auth;
+use crate::config::AdminApiSettings;
+use crate::plugins;
+use crate::runtime::{self, NewServerConfig};
+use crate::state::AppState;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::sync::{Arc, Weak};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// 每个 WebSocket 连接待发送消息的上限，队列满时暂停读取新命令并丢弃日志帧
+const OUTBOUND_CAPACITY: usize = 256;
+
+/// 每个日志订阅缓存的日志条数上限
+const LOG_SUBSCRIPTION_CAPACITY: usize = 256;
+
+/// 运行中的管理接口
+pub struct AdminApiHandle {
+    settings: AdminApiSettings,
+    shutdown_tx: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl AdminApiHandle {
+    /// 启动时使用的设置
+    pub fn settings(&self) -> &AdminApiSettings {
+        &self.settings
+    }
+
+    /// 停止监听，等待端口释放后返回
+    pub async fn stop(self) {
+        let _ = self.shutdown_tx.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// 管理接口处理请求所需的共享状态
+#[derive(Clone)]
+struct AdminApiState {
+    app_state: Weak<AppState>,
+    token: Arc<str>,
+}
+
+/// 启动管理接口，监听失败时返回错误
+///
+/// 修改设置的命令本身也经由管理接口调用，这里同步绑定端口，
+/// 避免命令的 future 类型递归包含自身。
+pub fn start(state: &Arc<AppState>, settings: AdminApiSettings) -> Result<AdminApiHandle, String> {
+    let addr = format!("{}:{}", settings.host, settings.port);
+    let listener = std::net::TcpListener::bind(&addr)
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        })
+        .map_err(|e| format!("管理接口监听 {} 失败: {}", addr, e))?;
+
+    let app = Router::new()
+        .route("/api/:command", any(handle_command))
+        .route("/ws", get(handle_websocket))
+        .with_state(AdminApiState {
+            app_state: Arc::downgrade(state),
+            token: Arc::from(settings.token.as_str()),
+        });
+
+    println!("管理接口启动于: {}", addr);
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let task = tokio::spawn(async move {
+        tokio::select! {
+            _ = shutdown_rx => {
+                println!("管理接口已停止");
+            }
+            result = axum::serve(listener, app) => {
+                if let Err(e) = result {
+                    eprintln!("管理接口运行出错: {}", e);
+                }
+            }
+        }
+    });
+
+    Ok(AdminApiHandle { settings, shutdown_tx, task })
+}
+
+/// 命令调用失败的原因
+#[derive(Debug)]
+pub enum CommandError {
+    /// 不存在或不允许远程调用的命令
+    UnknownCommand(String),
+    /// 参数缺失或格式错误
+    InvalidArgs(String),
+    /// 命令执行失败
+    Failed(String),
+}
+
+impl CommandError {
+    fn status(&self) -> StatusCode {
+        match self {
+            CommandError::UnknownCommand(_) => StatusCode::NOT_FOUND,
+            CommandError::InvalidArgs(_) => StatusCode::BAD_REQUEST,
+            CommandError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            CommandError::UnknownCommand(command) => format!("未知命令: {}", command),
+            CommandError::InvalidArgs(e) => format!("参数错误: {}", e),
+            CommandError::Failed(e) => e.clone(),
+        }
+    }
+}
+
+/// 调用命令，参数名同时支持 snake_case 与前端 `invoke` 使用的 camelCase
+pub async fn dispatch(state: &Arc<AppState>, command: &str, args: Value) -> Result<Value, CommandError> {
+    let args = normalize_args(args)?;
+
+    match command {
+        // 服务器
+        "start_server" => respond(runtime::start_server(state, arg(&args, "server_id")?).await),
+        "stop_server" => respond(runtime::stop_server(state, arg(&args, "server_id")?).await),
+        "get_server_status" => respond(runtime::get_server_status(state, arg(&args, "server_id")?).await),
+        "get_all_server_status" => respond(runtime::get_all_server_status(state).await),
+        "get_server_status_info" => respond(runtime::get_server_status_info(state).await),
+        "get_all_servers" => respond(runtime::get_all_servers(state).await),
+        "add_server_config" => {
+            let config: NewServerConfig = parse(Value::Object(args))?;
+            respond(runtime::add_server_config(state, config).await)
+        }
+        "update_server_config" => respond(runtime::update_server_config(state, arg(&args, "server")?).await),
+        "remove_server_config" => respond(runtime::remove_server_config(state, arg(&args, "server_id")?).await),
+        "set_server_enabled" => respond(runtime::set_server_enabled(state, arg(&args, "server_id")?, arg(&args, "enabled")?).await),
+        "get_config_path" => respond(runtime::get_config_path(state).await),
+        "get_app_settings" => respond(runtime::get_app_settings(state).await),
+        "update_app_settings" => respond(runtime::update_app_settings(state, arg(&args, "settings")?).await),
+        "get_app_version" => respond(runtime::get_app_version().await),
+
+        // 日志
+        "get_log_history" => respond(runtime::get_log_history(state).await),
+        "get_unknown_frames" => respond(runtime::get_unknown_frames(state).await),
+        "query_logs" => respond(runtime::query_logs(state, arg(&args, "query")?).await),
+        "clear_log_history" => respond(runtime::clear_log_history(state).await),
+
+        // 发送队列与发件箱
+        "get_send_queue_stats" => respond(runtime::get_send_queue_stats(state).await),
+        "get_outbox_messages" => respond(runtime::get_outbox_messages(state).await),
+        "cancel_outbox_message" => respond(runtime::cancel_outbox_message(state, arg(&args, "id")?).await),
+
+        // 机器人与消息
+        "get_bot_accounts" => respond(runtime::get_bot_accounts(state).await),
+        "get_friends" => respond(runtime::get_friends(state, arg(&args, "self_id")?).await),
+        "get_groups" => respond(runtime::get_groups(state, arg(&args, "self_id")?).await),
+        "refresh_bot_data" => respond(runtime::refresh_bot_data(state, opt_arg(&args, "self_id")?).await),
+        "send_private_message" => respond(runtime::send_private_message(
+            state,
+            arg(&args, "user_id")?,
+            arg(&args, "message")?,
+            opt_arg(&args, "self_id")?,
+        ).await),
+        "send_group_message" => respond(runtime::send_group_message(
+            state,
+            arg(&args, "group_id")?,
+            arg(&args, "message")?,
+            opt_arg(&args, "self_id")?,
+        ).await),
+
+        // 插件
+        "init_plugin_system" => respond(runtime::init_plugin_system(state).await),
+        "get_all_plugins" => respond(runtime::get_all_plugins(state).await),
+        "enable_plugin" => respond(runtime::enable_plugin(state, arg(&args, "plugin_id")?).await),
+        "disable_plugin" => respond(runtime::disable_plugin(state, arg(&args, "plugin_id")?).await),
+        "unload_plugin" => respond(runtime::unload_plugin(state, arg(&args, "plugin_id")?).await),
+        "get_plugin_stats" => respond(runtime::get_plugin_stats(state, arg(&args, "plugin_id")?).await),
+        "get_plugin_config" => respond(runtime::get_plugin_config(state, arg(&args, "plugin_name")?).await),
+        "update_plugin_config" => {
+            let config: plugins::config::PluginConfig = arg(&args, "config")?;
+            respond(runtime::update_plugin_config(state, config).await)
+        }
+        "get_global_plugin_config" => respond(runtime::get_global_plugin_config(state).await),
+        "update_global_plugin_config" => {
+            let config: plugins::config::GlobalPluginConfig = arg(&args, "config")?;
+            respond(runtime::update_global_plugin_config(state, config).await)
+        }
+
+        _ => Err(CommandError::UnknownCommand(command.to_string())),
+    }
+}
+
+/// 将命令返回值转换为 JSON
+fn respond<T: Serialize>(result: Result<T, String>) -> Result<Value, CommandError> {
+    let value = result.map_err(CommandError::Failed)?;
+    serde_json::to_value(value).map_err(|e| CommandError::Failed(format!("序列化返回值失败: {}", e)))
+}
+
+/// 参数必须是对象（`null` 视为无参数），顶层的 camelCase 参数名转换为 snake_case
+fn normalize_args(args: Value) -> Result<Map<String, Value>, CommandError> {
+    let args = match args {
+        Value::Null => Map::new(),
+        Value::Object(args) => args,
+        _ => return Err(CommandError::InvalidArgs("参数必须是 JSON 对象".to_string())),
+    };
+
+    Ok(args.into_iter()
+        .map(|(key, value)| (to_snake_case(&key), value))
+        .collect())
+}
+
+fn to_snake_case(key: &str) -> String {
+    let mut result = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            result.push('_');
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, CommandError> {
+    serde_json::from_value(value).map_err(|e| CommandError::InvalidArgs(e.to_string()))
+}
+
+/// 读取必填参数
+fn arg<T: DeserializeOwned>(args: &Map<String, Value>, name: &str) -> Result<T, CommandError> {
+    let value = args.get(name)
+        .cloned()
+        .ok_or_else(|| CommandError::InvalidArgs(format!("缺少参数 {}", name)))?;
+    serde_json::from_value(value).map_err(|e| CommandError::InvalidArgs(format!("{}: {}", name, e)))
+}
+
+/// 读取可选参数
+fn opt_arg<T: DeserializeOwned>(args: &Map<String, Value>, name: &str) -> Result<Option<T>, CommandError> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => arg(args, name).map(Some),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "ok": false, "error": message }))).into_response()
+}
+
+/// 鉴权并取得应用状态
+///
+/// 与 OneBot 服务器一致，浏览器中的 WebSocket 无法设置请求头时使用 `access_token` 查询参数。
+fn authorize(state: &AdminApiState, headers: &HeaderMap, uri: &Uri) -> Result<Arc<AppState>, (StatusCode, &'static str)> {
+    if !auth::verify_access_token(headers, uri, &state.token) {
+        return Err((StatusCode::UNAUTHORIZED, "访问令牌无效"));
+    }
+    state.app_state.upgrade()
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, "应用正在退出"))
+}
+
+/// 处理一次 HTTP 命令调用
+async fn handle_command(
+    State(state): State<AdminApiState>,
+    Path(command): Path<String>,
+    headers: HeaderMap,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let app_state = match authorize(&state, &headers, &uri) {
+        Ok(app_state) => app_state,
+        Err((status, message)) => return error_response(status, message.to_string()),
+    };
+
+    let args = if body.iter().all(u8::is_ascii_whitespace) {
+        Value::Null
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(args) => args,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, format!("请求体不是有效的 JSON: {}", e)),
+        }
+    };
+
+    match dispatch(&app_state, &command, args).await {
+        Ok(data) => Json(json!({ "ok": true, "data": data })).into_response(),
+        Err(e) => error_response(e.status(), e.message()),
+    }
+}
+
+/// WebSocket 上的命令调用
+#[derive(Debug, Deserialize)]
+struct WsRequest {
+    command: String,
+    #[serde(default)]
+    args: Value,
+    #[serde(default)]
+    echo: Value,
+}
+
+/// 处理 WebSocket 握手
+async fn handle_websocket(
+    State(state): State<AdminApiState>,
+    headers: HeaderMap,
+    uri: Uri,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if let Err((status, message)) = authorize(&state, &headers, &uri) {
+        return error_response(status, message.to_string());
+    }
+
+    ws.on_upgrade(move |socket| serve_websocket(socket, state.app_state))
+}
+
+/// 处理一个 WebSocket 连接，命令并发执行，结果按完成顺序返回
+///
+/// 每条命令执行前先占用发送队列的一个位置，同时执行的命令数不会超过队列容量。
+async fn serve_websocket(socket: WebSocket, app_state: Weak<AppState>) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Value>(OUTBOUND_CAPACITY);
+
+    let sender_task = tokio::spawn(async move {
+        while let Some(value) = rx.recv().await {
+            if sink.send(Message::Text(value.to_string())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut log_task: Option<JoinHandle<()>> = None;
+
+    while let Some(Ok(message)) = stream.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let request: WsRequest = match serde_json::from_str(&text) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = tx.send(json!({ "type": "response", "ok": false, "error": format!("请求格式错误: {}", e) })).await;
+                continue;
+            }
+        };
+
+        let Some(state) = app_state.upgrade() else {
+            break;
+        };
+
+        // 日志订阅绑定在连接上，连接关闭时一并结束
+        if request.command == "subscribe_logs" {
+            if log_task.is_none() {
+                let mut logs = state.logs.subscribe_bounded(LOG_SUBSCRIPTION_CAPACITY);
+                let tx = tx.clone();
+                log_task = Some(tokio::spawn(async move {
+                    while let Some(entry) = logs.recv().await {
+                        if let Err(mpsc::error::TrySendError::Closed(_)) = tx.try_send(json!({ "type": "log", "data": entry })) {
+                            break;
+                        }
+                    }
+                }));
+            }
+            let _ = tx.send(json!({ "type": "response", "echo": request.echo, "ok": true, "data": null })).await;
+            continue;
+        }
+
+        let Ok(permit) = tx.clone().reserve_owned().await else {
+            break;
+        };
+        tokio::spawn(async move {
+            let response = match dispatch(&state, &request.command, request.args).await {
+                Ok(data) => json!({ "type": "response", "echo": request.echo, "ok": true, "data": data }),
+                Err(e) => json!({ "type": "response", "echo": request.echo, "ok": false, "error": e.message() }),
+            };
+            permit.send(response);
+        });
+    }
+
+    if let Some(log_task) = log_task {
+        log_task.abort();
+    }
+    drop(tx);
+    let _ = sender_task.await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigManager, LogEntry, LogLevel};
+    use tokio_tungstenite::tungstenite;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    async fn start_admin_api(dir: &std::path::Path) -> (Arc<AppState>, AdminApiHandle, u16) {
+        let state = AppState::new();
+        runtime::initialize(&state, ConfigManager::from_dir(dir.to_path_buf()).unwrap()).await;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let settings = AdminApiSettings {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port,
+            token: "secret".to_string(),
+        };
+        let handle = start(&state, settings).unwrap();
+        (state, handle, port)
+    }
+
+    /// 读取下一条文本帧
+    async fn next_json<S>(stream: &mut S) -> Value
+    where
+        S: futures_util::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+                .await.unwrap().unwrap().unwrap();
+            if let tungstenite::Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rest_rejects_bad_token_and_runs_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_state, handle, port) = start_admin_api(dir.path()).await;
+        let url = format!("http://127.0.0.1:{}/api/get_all_server_status", port);
+        let client = reqwest::Client::new();
+
+        let response = client.post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client.post(&url).bearer_auth("wrong").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client.post(&url).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body, json!({ "ok": true, "data": [] }));
+
+        let response = client.post(format!("http://127.0.0.1:{}/api/no_such_command", port))
+            .bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn websocket_rejects_bad_token_and_runs_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state, handle, port) = start_admin_api(dir.path()).await;
+
+        let error = tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}/ws?access_token=wrong", port))
+            .await.unwrap_err();
+        assert!(matches!(error, tungstenite::Error::Http(ref response) if response.status() == 401), "{}", error);
+
+        let mut request = format!("ws://127.0.0.1:{}/ws", port).into_client_request().unwrap();
+        request.headers_mut().insert("Authorization", "Bearer secret".parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+
+        let call = json!({ "command": "get_all_server_status", "echo": 7 });
+        socket.send(tungstenite::Message::Text(call.to_string())).await.unwrap();
+        assert_eq!(next_json(&mut socket).await, json!({ "type": "response", "echo": 7, "ok": true, "data": [] }));
+
+        let call = json!({ "command": "subscribe_logs", "echo": 8 });
+        socket.send(tungstenite::Message::Text(call.to_string())).await.unwrap();
+        assert_eq!(next_json(&mut socket).await["echo"], 8);
+
+        state.logs.add(LogEntry::new(LogLevel::Info, "test".to_string(), "hello".to_string(), None));
+        let frame = next_json(&mut socket).await;
+        assert_eq!(frame["type"], "log");
+        assert_eq!(frame["data"]["content"], "hello");
+
+        handle.stop().await;
+    }
+}
@@ -0,0 +1,87 @@
+use axum::http::{HeaderMap, Uri};
+
+/// 校验请求携带的访问令牌
+///
+/// 优先读取 `Authorization: Bearer <token>` 请求头（兼容 `Token <token>`），
+/// 不存在时回退到 `access_token` 查询参数。查询参数先做百分号解码，
+/// `+` 按字面保留，令牌中的 `+`、`/`、`=` 无论是否编码都能匹配。
+pub fn verify_access_token(headers: &HeaderMap, uri: &Uri, token: &str) -> bool {
+    if let Some(value) = headers.get("Authorization").and_then(|v| v.to_str().ok()) {
+        let provided = value.strip_prefix("Bearer ")
+            .or_else(|| value.strip_prefix("Token "))
+            .unwrap_or(value)
+            .trim();
+        return constant_time_eq(provided, token);
+    }
+
+    uri.query()
+        .map(|query| {
+            query.split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .filter(|(key, _)| *key == "access_token")
+                .filter_map(|(_, value)| percent_decode(value))
+                .any(|value| constant_time_eq(&value, token))
+        })
+        .unwrap_or(false)
+}
+
+/// 比较令牌，耗时与不匹配的位置无关
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 百分号解码，编码不完整或结果不是 UTF-8 时返回 `None`
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(query: &str) -> Uri {
+        format!("/ws?{}", query).parse().unwrap()
+    }
+
+    #[test]
+    fn header_token_is_checked() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer abc".parse().unwrap());
+        assert!(verify_access_token(&headers, &uri(""), "abc"));
+        assert!(!verify_access_token(&headers, &uri(""), "abd"));
+
+        // 请求头优先，查询参数不再生效
+        assert!(!verify_access_token(&headers, &uri("access_token=other"), "other"));
+    }
+
+    #[test]
+    fn query_token_is_percent_decoded() {
+        let token = "a+b/c==";
+        let headers = HeaderMap::new();
+        assert!(verify_access_token(&headers, &uri("access_token=a%2Bb%2Fc%3D%3D"), token));
+        assert!(verify_access_token(&headers, &uri("x=1&access_token=a+b/c=="), token));
+        assert!(!verify_access_token(&headers, &uri("access_token=a%2"), token));
+        assert!(!verify_access_token(&headers, &uri(""), token));
+    }
+
+    #[test]
+    fn constant_time_eq_compares_length_and_content() {
+        assert!(constant_time_eq("token", "token"));
+        assert!(!constant_time_eq("token", "tokem"));
+        assert!(!constant_time_eq("token", "token2"));
+    }
+}
@@ -0,0 +1,22 @@
+//! linbot2 无界面守护进程
+//!
+//! 用法: linbot2d [--config-dir <目录>] [--work-dir <目录>] [--log-file <文件>]
+
+use linbot2_lib::daemon::{self, DaemonOptions};
+
+fn main() {
+    let options = match DaemonOptions::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("用法: linbot2d [--config-dir <目录>] [--work-dir <目录>] [--log-file <文件>]");
+            std::process::exit(2);
+        }
+    };
+
+    let runtime = tokio::runtime::Runtime::new().expect("创建 tokio 运行时失败");
+    if let Err(e) = runtime.block_on(daemon::run(options)) {
+        eprintln!("linbot2d 运行失败: {}", e);
+        std::process::exit(1);
+    }
+}
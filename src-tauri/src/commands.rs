@@ -0,0 +1,406 @@
+use std::sync::Arc;
+use tauri::{Emitter, Manager};
+
+use crate::config::{AppSettings, ConfigManager, LogEntry, ServerConfig};
+use crate::onebot::{BotAccount, ConnectionMode, Friend, Group, SendMessageResponse};
+use crate::log_storage;
+use crate::outbox;
+use crate::plugins;
+use crate::runtime::{self, NewServerConfig, ServerRuntimeStatus, ServerStatusInfo};
+use crate::send_queue;
+use crate::state::AppState;
+
+// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+#[tauri::command]
+fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+/// 启动指定的服务器
+#[tauri::command]
+async fn start_server(state: tauri::State<'_, Arc<AppState>>, server_id: String) -> Result<String, String> {
+    runtime::start_server(&state, server_id).await
+}
+
+/// 停止指定的服务器
+#[tauri::command]
+async fn stop_server(state: tauri::State<'_, Arc<AppState>>, server_id: String) -> Result<String, String> {
+    runtime::stop_server(&state, server_id).await
+}
+
+/// 获取指定服务器的运行状态
+#[tauri::command]
+async fn get_server_status(state: tauri::State<'_, Arc<AppState>>, server_id: String) -> Result<ServerRuntimeStatus, String> {
+    runtime::get_server_status(&state, server_id).await
+}
+
+/// 获取所有已配置服务器的运行状态
+#[tauri::command]
+async fn get_all_server_status(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<ServerRuntimeStatus>, String> {
+    runtime::get_all_server_status(&state).await
+}
+
+/// 初始化配置管理器
+#[tauri::command]
+async fn init_config_manager(app_handle: tauri::AppHandle, state: tauri::State<'_, Arc<AppState>>) -> Result<String, String> {
+    let manager = ConfigManager::new(&app_handle)
+        .map_err(|e| format!("初始化配置管理器失败: {}", e))?;
+
+    Ok(runtime::initialize(&state, manager).await)
+}
+
+/// 获取所有服务器配置
+#[tauri::command]
+async fn get_all_servers(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<ServerConfig>, String> {
+    runtime::get_all_servers(&state).await
+}
+
+/// 添加服务器配置
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn add_server_config(
+    state: tauri::State<'_, Arc<AppState>>,
+    name: String,
+    host: String,
+    port: u16,
+    access_token: Option<String>,
+    auto_start: Option<bool>,
+    connection_type: Option<ConnectionMode>,
+    url: Option<String>,
+    secret: Option<String>,
+    post_path: Option<String>,
+    tls_cert_path: Option<String>,
+    tls_key_path: Option<String>,
+    ip_allowlist: Option<Vec<String>>,
+    ip_denylist: Option<Vec<String>>,
+) -> Result<ServerConfig, String> {
+    runtime::add_server_config(&state, NewServerConfig {
+        name,
+        host,
+        port,
+        access_token,
+        auto_start,
+        connection_type,
+        url,
+        secret,
+        post_path,
+        tls_cert_path,
+        tls_key_path,
+        ip_allowlist,
+        ip_denylist,
+    }).await
+}
+
+/// 更新服务器配置
+#[tauri::command]
+async fn update_server_config(state: tauri::State<'_, Arc<AppState>>, server: ServerConfig) -> Result<(), String> {
+    runtime::update_server_config(&state, server).await
+}
+
+/// 删除服务器配置
+#[tauri::command]
+async fn remove_server_config(state: tauri::State<'_, Arc<AppState>>, server_id: String) -> Result<(), String> {
+    runtime::remove_server_config(&state, server_id).await
+}
+
+/// 设置服务器启用状态（启用时启动监听，禁用时停止监听）
+#[tauri::command]
+async fn set_server_enabled(state: tauri::State<'_, Arc<AppState>>, server_id: String, enabled: bool) -> Result<(), String> {
+    runtime::set_server_enabled(&state, server_id, enabled).await
+}
+
+/// 获取配置文件路径
+#[tauri::command]
+async fn get_config_path(state: tauri::State<'_, Arc<AppState>>) -> Result<String, String> {
+    runtime::get_config_path(&state).await
+}
+
+/// 获取应用设置
+#[tauri::command]
+async fn get_app_settings(state: tauri::State<'_, Arc<AppState>>) -> Result<AppSettings, String> {
+    runtime::get_app_settings(&state).await
+}
+
+/// 更新应用设置
+#[tauri::command]
+async fn update_app_settings(state: tauri::State<'_, Arc<AppState>>, settings: AppSettings) -> Result<(), String> {
+    runtime::update_app_settings(&state, settings).await
+}
+
+/// 获取日志历史
+#[tauri::command]
+async fn get_log_history(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<LogEntry>, String> {
+    runtime::get_log_history(&state).await
+}
+
+/// 获取无法解析的 OneBot 数据
+#[tauri::command]
+async fn get_unknown_frames(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<LogEntry>, String> {
+    runtime::get_unknown_frames(&state).await
+}
+
+/// 按条件查询持久化的日志
+#[tauri::command]
+async fn query_logs(state: tauri::State<'_, Arc<AppState>>, query: log_storage::LogQuery) -> Result<log_storage::LogQueryResult, String> {
+    runtime::query_logs(&state, query).await
+}
+
+/// 清空日志历史
+#[tauri::command]
+async fn clear_log_history(state: tauri::State<'_, Arc<AppState>>) -> Result<(), String> {
+    runtime::clear_log_history(&state).await
+}
+
+/// 订阅实时日志
+#[tauri::command]
+async fn subscribe_logs(window: tauri::Window, state: tauri::State<'_, Arc<AppState>>) -> Result<(), String> {
+    let mut rx = state.logs.subscribe();
+    
+    // 启动发送任务
+    tokio::spawn(async move {
+        while let Some(log_entry) = rx.recv().await {
+            if let Err(e) = window.emit("log-entry", &log_entry) {
+                eprintln!("发送日志事件失败: {}", e);
+                break;
+            }
+        }
+    });
+    
+    Ok(())
+}
+
+/// 获取各机器人的发送队列统计
+#[tauri::command]
+async fn get_send_queue_stats(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<send_queue::SendQueueStats>, String> {
+    runtime::get_send_queue_stats(&state).await
+}
+
+/// 获取发件箱中等待发送的消息
+#[tauri::command]
+async fn get_outbox_messages(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<outbox::OutboxMessage>, String> {
+    runtime::get_outbox_messages(&state).await
+}
+
+/// 取消发件箱中的消息
+#[tauri::command]
+async fn cancel_outbox_message(state: tauri::State<'_, Arc<AppState>>, id: String) -> Result<(), String> {
+    runtime::cancel_outbox_message(&state, id).await
+}
+
+/// 获取所有机器人账号
+#[tauri::command]
+async fn get_bot_accounts(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<BotAccount>, String> {
+    runtime::get_bot_accounts(&state).await
+}
+
+/// 获取指定机器人的好友列表
+#[tauri::command]
+async fn get_friends(state: tauri::State<'_, Arc<AppState>>, self_id: i64) -> Result<Vec<Friend>, String> {
+    runtime::get_friends(&state, self_id).await
+}
+
+/// 获取指定机器人的群聊列表
+#[tauri::command]
+async fn get_groups(state: tauri::State<'_, Arc<AppState>>, self_id: i64) -> Result<Vec<Group>, String> {
+    runtime::get_groups(&state, self_id).await
+}
+
+/// 刷新机器人数据（清除缓存）
+#[tauri::command]
+async fn refresh_bot_data(state: tauri::State<'_, Arc<AppState>>, self_id: Option<i64>) -> Result<(), String> {
+    runtime::refresh_bot_data(&state, self_id).await
+}
+
+/// 发送私聊消息
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn send_private_message(
+    state: tauri::State<'_, Arc<AppState>>,
+    userId: i64,
+    message: String,
+    selfId: Option<i64>,
+) -> Result<SendMessageResponse, String> {
+    runtime::send_private_message(&state, userId, message, selfId).await
+}
+
+/// 发送群聊消息
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn send_group_message(
+    state: tauri::State<'_, Arc<AppState>>,
+    groupId: i64,
+    message: String,
+    selfId: Option<i64>,
+) -> Result<SendMessageResponse, String> {
+    runtime::send_group_message(&state, groupId, message, selfId).await
+}
+
+/// 获取用户头像
+#[tauri::command]
+async fn get_user_avatar(user_id: i64) -> Result<String, String> {
+    // OneBot 标准中没有直接的头像API，通常使用QQ头像链接
+    Ok(format!("https://q1.qlogo.cn/g?b=qq&nk={}&s=640", user_id))
+}
+
+/// 获取群聊头像
+#[tauri::command]
+async fn get_group_avatar(group_id: i64) -> Result<String, String> {
+    // OneBot 标准中没有直接的群头像API，通常使用QQ群头像链接
+    Ok(format!("https://p.qlogo.cn/gh/{}/{}/640/", group_id, group_id))
+}
+
+/// 获取应用版本
+#[tauri::command]
+async fn get_app_version() -> Result<String, String> {
+    runtime::get_app_version().await
+}
+
+/// 初始化插件系统
+#[tauri::command]
+async fn init_plugin_system(state: tauri::State<'_, Arc<AppState>>) -> Result<String, String> {
+    runtime::init_plugin_system(&state).await
+}
+
+/// 获取所有插件
+#[tauri::command]
+async fn get_all_plugins(state: tauri::State<'_, Arc<AppState>>) -> Result<Vec<plugins::PluginMetadata>, String> {
+    runtime::get_all_plugins(&state).await
+}
+
+/// 启用插件
+#[tauri::command]
+async fn enable_plugin(state: tauri::State<'_, Arc<AppState>>, plugin_id: String) -> Result<(), String> {
+    runtime::enable_plugin(&state, plugin_id).await
+}
+
+/// 禁用插件
+#[tauri::command]
+async fn disable_plugin(state: tauri::State<'_, Arc<AppState>>, plugin_id: String) -> Result<(), String> {
+    runtime::disable_plugin(&state, plugin_id).await
+}
+
+/// 卸载插件
+#[tauri::command]
+async fn unload_plugin(state: tauri::State<'_, Arc<AppState>>, plugin_id: String) -> Result<(), String> {
+    runtime::unload_plugin(&state, plugin_id).await
+}
+
+/// 获取插件统计信息
+#[tauri::command]
+async fn get_plugin_stats(state: tauri::State<'_, Arc<AppState>>, plugin_id: String) -> Result<plugins::PluginStats, String> {
+    runtime::get_plugin_stats(&state, plugin_id).await
+}
+
+/// 获取插件配置
+#[tauri::command]
+async fn get_plugin_config(state: tauri::State<'_, Arc<AppState>>, plugin_name: String) -> Result<plugins::config::PluginConfig, String> {
+    runtime::get_plugin_config(&state, plugin_name).await
+}
+
+/// 更新插件配置
+#[tauri::command]
+async fn update_plugin_config(state: tauri::State<'_, Arc<AppState>>, config: plugins::config::PluginConfig) -> Result<(), String> {
+    runtime::update_plugin_config(&state, config).await
+}
+
+/// 获取全局插件配置
+#[tauri::command]
+async fn get_global_plugin_config(state: tauri::State<'_, Arc<AppState>>) -> Result<plugins::config::GlobalPluginConfig, String> {
+    runtime::get_global_plugin_config(&state).await
+}
+
+/// 更新全局插件配置
+#[tauri::command]
+async fn update_global_plugin_config(state: tauri::State<'_, Arc<AppState>>, config: plugins::config::GlobalPluginConfig) -> Result<(), String> {
+    runtime::update_global_plugin_config(&state, config).await
+}
+
+/// 获取详细的服务器状态信息（汇总所有运行中的服务器）
+#[tauri::command]
+async fn get_server_status_info(state: tauri::State<'_, Arc<AppState>>) -> Result<ServerStatusInfo, String> {
+    runtime::get_server_status_info(&state).await
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // 应用状态注册到 Tauri，命令通过 State 参数访问
+            let state = AppState::new();
+            app.manage(Arc::clone(&state));
+
+            // 前端事件统一通过应用句柄推送
+            let app_handle = app.handle().clone();
+            let emitter_handle = app_handle.clone();
+            state.set_emitter(Arc::new(move |event, payload| {
+                if let Err(e) = emitter_handle.emit(event, payload) {
+                    eprintln!("发送前端事件 {} 失败: {}", event, e);
+                }
+            }));
+
+            // 应用启动时初始化配置管理器
+            tauri::async_runtime::spawn(async move {
+                runtime::spawn_heartbeat_watchdog(&state);
+
+                match ConfigManager::new(&app_handle) {
+                    Ok(manager) => {
+                        runtime::initialize(&state, manager).await;
+
+                        // 自动启动服务器
+                        runtime::auto_start_servers(&state).await;
+                    }
+                    Err(e) => {
+                        eprintln!("初始化配置管理器失败: {}", e);
+                    }
+                }
+            });
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            start_server,
+            stop_server,
+            get_server_status,
+            get_all_server_status,
+            init_config_manager,
+            get_all_servers,
+            add_server_config,
+            update_server_config,
+            remove_server_config,
+            set_server_enabled,
+            get_config_path,
+            get_app_settings,
+            update_app_settings,
+            get_log_history,
+            get_unknown_frames,
+            query_logs,
+            get_send_queue_stats,
+            get_outbox_messages,
+            cancel_outbox_message,
+            clear_log_history,
+            subscribe_logs,
+            get_bot_accounts,
+            get_friends,
+            get_groups,
+            refresh_bot_data,
+            get_server_status_info,
+            send_private_message,
+            send_group_message,
+            get_user_avatar,
+            get_group_avatar,
+            get_app_version,
+            init_plugin_system,
+            get_all_plugins,
+            enable_plugin,
+            disable_plugin,
+            unload_plugin,
+            get_plugin_stats,
+            get_plugin_config,
+            update_plugin_config,
+            get_global_plugin_config,
+            update_global_plugin_config
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
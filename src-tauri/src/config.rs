@@ -2,8 +2,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::fs;
+use std::time::Duration;
+#[cfg(feature = "gui")]
 use tauri::Manager;
 
+use crate::onebot::{ConnectionMode, OneBotConfig};
+
 /// 服务器配置信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -12,8 +16,24 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub access_token: Option<String>,
-    pub enabled: bool,  // 是否启用（重启时会重置为false）
+    pub enabled: bool,  // 是否启用（启动时按 auto_start 重新确定）
     pub auto_start: bool, // 是否自动启动
+    #[serde(default)]
+    pub connection_type: ConnectionMode, // 连接方式（反向/正向 WebSocket）
+    #[serde(default)]
+    pub url: Option<String>, // 正向 WebSocket 地址
+    #[serde(default)]
+    pub secret: Option<String>, // HTTP POST 上报签名密钥
+    #[serde(default)]
+    pub post_path: Option<String>, // HTTP POST 上报路径，为空时为 /
+    #[serde(default)]
+    pub tls_cert_path: Option<String>, // TLS 证书路径（PEM），与私钥同时配置时启用 wss://，仅反向 WebSocket 支持
+    #[serde(default)]
+    pub tls_key_path: Option<String>, // TLS 私钥路径（PEM）
+    #[serde(default)]
+    pub ip_allowlist: Vec<String>, // 允许连接或上报的 IP 段（CIDR），为空时不限制；正向 WebSocket 不适用
+    #[serde(default)]
+    pub ip_denylist: Vec<String>, // 拒绝连接的 IP 段（CIDR）
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -29,10 +49,56 @@ impl ServerConfig {
             access_token,
             enabled: false,
             auto_start: false,
+            connection_type: ConnectionMode::default(),
+            url: None,
+            secret: None,
+            post_path: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            ip_allowlist: Vec::new(),
+            ip_denylist: Vec::new(),
             created_at: now,
             updated_at: now,
         }
     }
+
+    /// 用于展示的连接地址：监听模式为监听地址，正向模式为目标 URL
+    pub fn endpoint(&self) -> String {
+        match self.connection_type {
+            ConnectionMode::ReverseWs | ConnectionMode::HttpPost => format!("{}:{}", self.host, self.port),
+            ConnectionMode::ForwardWs => self.url.clone().unwrap_or_default(),
+        }
+    }
+
+    /// 校验 TLS 配置：证书与私钥需同时填写，且只有反向 WebSocket 模式支持
+    pub fn validate_tls(&self) -> Result<(), String> {
+        let (has_cert, has_key) = (self.tls_cert_path.is_some(), self.tls_key_path.is_some());
+        if has_cert != has_key {
+            return Err("TLS 证书与私钥路径需要同时填写".to_string());
+        }
+        if has_cert && self.connection_type != ConnectionMode::ReverseWs {
+            return Err("只有反向 WebSocket 模式支持 TLS 证书配置".to_string());
+        }
+        Ok(())
+    }
+
+    /// 转换为 OneBot 服务器配置
+    pub fn to_onebot_config(&self) -> OneBotConfig {
+        OneBotConfig {
+            host: self.host.clone(),
+            port: self.port,
+            access_token: self.access_token.clone(),
+            secret: self.secret.clone(),
+            mode: self.connection_type,
+            url: self.url.clone(),
+            post_path: self.post_path.clone(),
+            tls_cert_path: self.tls_cert_path.clone(),
+            tls_key_path: self.tls_key_path.clone(),
+            max_connections: None,
+            ip_allowlist: self.ip_allowlist.clone(),
+            ip_denylist: self.ip_denylist.clone(),
+        }
+    }
 }
 
 /// 应用配置
@@ -54,6 +120,128 @@ pub struct AppSettings {
     pub auto_scroll_logs: bool,       // 是否自动滚动日志
     pub max_log_entries: u32,         // 最大日志条目数
     pub log_buffer_size: u32,         // 日志缓冲区大小
+    // API 调用相关设置
+    #[serde(default = "default_api_timeout_secs")]
+    pub api_timeout_secs: u64,        // API 调用默认超时（秒）
+    #[serde(default = "default_api_action_timeouts")]
+    pub api_action_timeouts: HashMap<String, u64>, // 按动作覆盖的超时（秒）
+    // 发送限速设置
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    // 发件箱设置
+    #[serde(default)]
+    pub outbox: OutboxSettings,
+    // 本地管理接口设置
+    #[serde(default)]
+    pub admin_api: AdminApiSettings,
+    // 日志持久化设置
+    #[serde(default)]
+    pub log_storage: LogStorageSettings,
+}
+
+/// 日志持久化设置：日志按分段 JSONL 文件保存在配置目录的 logs 子目录中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogStorageSettings {
+    pub enabled: bool,
+    pub max_segment_mb: u64, // 单个分段文件的大小上限，超出后写入新分段
+    pub max_total_mb: u64,   // 所有分段的总大小上限，超出后删除最旧的分段
+    pub retention_days: u64, // 分段保留天数，0 表示不按时间删除
+}
+
+impl Default for LogStorageSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_segment_mb: 5,
+            max_total_mb: 100,
+            retention_days: 30,
+        }
+    }
+}
+
+/// 本地管理接口设置：供运维脚本通过 HTTP / WebSocket 调用与桌面端相同的命令
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminApiSettings {
+    pub enabled: bool,
+    pub host: String,  // 默认只监听本机
+    pub port: u16,
+    pub token: String, // 访问令牌，为空时不启动
+}
+
+impl Default for AdminApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "127.0.0.1".to_string(),
+            port: 6790,
+            token: String::new(),
+        }
+    }
+}
+
+/// 发件箱设置：机器人离线时暂存发送的消息，上线后按顺序重放
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxSettings {
+    pub enabled: bool,
+    pub expiry_secs: i64, // 消息在发件箱中的保留时间（秒）
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            expiry_secs: 3600,
+        }
+    }
+}
+
+/// 发送消息限速设置（按机器人计算），默认关闭，需要时在配置文件中启用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    pub global_rate: f64,       // 每个机器人每秒可发送的消息数
+    pub global_burst: u32,      // 每个机器人允许的突发消息数
+    pub group_rate: f64,        // 每个群每秒可发送的消息数
+    pub group_burst: u32,       // 每个群允许的突发消息数
+    pub jitter_ms: u64,         // 放行前随机等待的最长时间（毫秒）
+    pub max_queue_depth: usize, // 排队上限，超出后直接丢弃
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            global_rate: 1.0,
+            global_burst: 5,
+            group_rate: 0.5,
+            group_burst: 3,
+            jitter_ms: 300,
+            max_queue_depth: 100,
+        }
+    }
+}
+
+fn default_api_timeout_secs() -> u64 {
+    10
+}
+
+fn default_api_action_timeouts() -> HashMap<String, u64> {
+    // 大群的成员列表返回较慢
+    HashMap::from([("get_group_member_list".to_string(), 60)])
+}
+
+impl AppSettings {
+    /// 获取指定 API 动作的超时时间
+    pub fn api_timeout(&self, action: &str) -> Duration {
+        let secs = self.api_action_timeouts.get(action)
+            .copied()
+            .unwrap_or(self.api_timeout_secs);
+        Duration::from_secs(secs.max(1))
+    }
 }
 
 impl Default for AppConfig {
@@ -69,6 +257,12 @@ impl Default for AppConfig {
                 auto_scroll_logs: true,      // 默认自动滚动
                 max_log_entries: 1000,       // 最大1000条日志
                 log_buffer_size: 100,        // 缓冲区100条
+                api_timeout_secs: default_api_timeout_secs(),
+                api_action_timeouts: default_api_action_timeouts(),
+                rate_limit: RateLimitSettings::default(),
+                outbox: OutboxSettings::default(),
+                admin_api: AdminApiSettings::default(),
+                log_storage: LogStorageSettings::default(),
             },
         }
     }
@@ -82,18 +276,24 @@ pub struct ConfigManager {
 
 impl ConfigManager {
     /// 创建新的配置管理器
+    #[cfg(feature = "gui")]
     pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // 获取应用数据目录
         let app_data_dir = app_handle
             .path()
             .app_config_dir()
             .map_err(|e| format!("获取应用目录失败: {}", e))?;
-        
+
+        Self::from_dir(app_data_dir)
+    }
+
+    /// 使用指定目录创建配置管理器，目录不存在时自动创建
+    pub fn from_dir(config_dir: PathBuf) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // 确保配置目录存在
-        fs::create_dir_all(&app_data_dir)
+        fs::create_dir_all(&config_dir)
             .map_err(|e| format!("创建配置目录失败: {}", e))?;
         
-        let config_path = app_data_dir.join("config.json");
+        let config_path = config_dir.join("config.json");
         
         let mut manager = Self {
             config_path,
@@ -113,11 +313,7 @@ impl ConfigManager {
             
             // 尝试解析配置文件
             match serde_json::from_str::<AppConfig>(&config_str) {
-                Ok(mut config) => {
-                    // 重置所有服务器的enabled状态为false
-                    for server in config.servers.values_mut() {
-                        server.enabled = false;
-                    }
+                Ok(config) => {
                     self.config = config;
                 }
                 Err(e) => {
@@ -144,11 +340,7 @@ impl ConfigManager {
                             
                             // 重新尝试解析
                             match serde_json::from_value::<AppConfig>(value) {
-                                Ok(mut config) => {
-                                    // 重置所有服务器的enabled状态为false
-                                    for server in config.servers.values_mut() {
-                                        server.enabled = false;
-                                    }
+                                Ok(config) => {
                                     self.config = config;
                                     
                                     // 保存更新后的配置
@@ -229,7 +421,6 @@ impl ConfigManager {
     }
     
     /// 获取指定服务器配置
-    #[allow(dead_code)]
     pub fn get_server(&self, server_id: &str) -> Option<&ServerConfig> {
         self.config.servers.get(server_id)
     }
@@ -239,6 +430,13 @@ impl ConfigManager {
         self.config_path.clone()
     }
     
+    /// 获取配置目录
+    pub fn get_config_dir(&self) -> PathBuf {
+        self.config_path.parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default()
+    }
+
     /// 获取应用设置
     #[allow(dead_code)]
     pub fn get_settings(&self) -> &AppSettings {
@@ -254,7 +452,7 @@ impl ConfigManager {
 }
 
 /// 日志条目类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Debug,
@@ -269,7 +467,7 @@ pub struct LogEntry {
     pub id: String,
     pub timestamp: i64,
     pub level: LogLevel,
-    pub category: String,  // "message", "heartbeat", "lifecycle", "notice", "request"
+    pub category: String,  // "message", "heartbeat", "lifecycle", "notice", "request", "bot", "unknown"
     pub content: String,
     pub raw_data: Option<serde_json::Value>,
     // 消息特定字段
@@ -0,0 +1,181 @@
+//! 无界面守护进程
+//!
+//! 与桌面端共用 `config.json`、`plugins.toml` 与运行时逻辑，
+//! 只是不创建窗口，日志输出到标准输出或文件，收到 SIGTERM / Ctrl+C 后停止所有服务器再退出。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::config::{ConfigManager, LogEntry, LogLevel};
+use crate::runtime;
+use crate::state::AppState;
+
+/// 与桌面端一致的应用标识，用于定位默认配置目录
+const APP_IDENTIFIER: &str = "com.linbot2.app";
+
+/// 指定配置目录的环境变量
+pub const CONFIG_DIR_ENV: &str = "LINBOT2_CONFIG_DIR";
+
+/// 守护进程启动选项
+#[derive(Debug, Clone, Default)]
+pub struct DaemonOptions {
+    /// 存放 config.json 与 outbox.json 的目录，为空时使用默认目录
+    pub config_dir: Option<PathBuf>,
+    /// 工作目录，`config/plugins.toml` 与 `plugins/` 位于其中，为空时使用配置目录
+    pub work_dir: Option<PathBuf>,
+    /// 日志文件路径，为空时输出到标准输出
+    pub log_file: Option<PathBuf>,
+}
+
+impl DaemonOptions {
+    /// 解析命令行参数（不含程序名）
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let mut value = || {
+                inline_value.clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("参数 {} 缺少取值", flag))
+            };
+
+            match flag.as_str() {
+                "--config-dir" => options.config_dir = Some(PathBuf::from(value()?)),
+                "--work-dir" => options.work_dir = Some(PathBuf::from(value()?)),
+                "--log-file" => options.log_file = Some(PathBuf::from(value()?)),
+                _ => return Err(format!("未知参数: {}", flag)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// 实际使用的配置目录：命令行参数 > 环境变量 > 桌面端默认目录
+    pub fn resolve_config_dir(&self) -> Result<PathBuf, String> {
+        if let Some(ref dir) = self.config_dir {
+            return Ok(dir.clone());
+        }
+        if let Some(dir) = std::env::var_os(CONFIG_DIR_ENV) {
+            return Ok(PathBuf::from(dir));
+        }
+        dirs::config_dir()
+            .map(|dir| dir.join(APP_IDENTIFIER))
+            .ok_or_else(|| format!("无法确定配置目录，请使用 --config-dir 或 {} 指定", CONFIG_DIR_ENV))
+    }
+}
+
+/// 运行守护进程，直到收到退出信号
+pub async fn run(options: DaemonOptions) -> Result<(), String> {
+    let state = AppState::new();
+    if let Some(ref dir) = options.work_dir {
+        state.set_work_dir(dir.clone());
+    }
+
+    // 先订阅日志，启动过程中的日志也会被输出
+    let log_writer = tokio::spawn(write_logs(state.logs.subscribe(), options.log_file.clone()));
+
+    let config_dir = options.resolve_config_dir()?;
+    let manager = ConfigManager::from_dir(config_dir)
+        .map_err(|e| format!("初始化配置管理器失败: {}", e))?;
+    runtime::initialize(&state, manager).await;
+    runtime::spawn_heartbeat_watchdog(&state);
+
+    match runtime::init_plugin_system(&state).await {
+        Ok(message) => println!("{}", message),
+        Err(e) => log(&state, LogLevel::Error, format!("[ERROR] {}", e)),
+    }
+
+    runtime::auto_start_servers(&state).await;
+    log(&state, LogLevel::Info, format!("[INFO] linbot2d 已启动，运行中的服务器: {}", state.servers.lock().await.len()));
+
+    wait_for_shutdown().await;
+
+    log(&state, LogLevel::Info, "[INFO] 收到退出信号，正在停止服务器".to_string());
+    runtime::shutdown(&state).await;
+    log(&state, LogLevel::Info, "[INFO] linbot2d 已退出".to_string());
+
+    runtime::close_logs(&state).await;
+    let _ = log_writer.await;
+    Ok(())
+}
+
+/// 写入一条守护进程日志
+fn log(state: &Arc<AppState>, level: LogLevel, content: String) {
+    state.logs.add(LogEntry::new(level, "daemon".to_string(), content, None));
+}
+
+/// 等待 SIGTERM 或 Ctrl+C
+async fn wait_for_shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => eprintln!("监听 SIGTERM 失败: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("监听 Ctrl+C 失败: {}", e);
+    }
+}
+
+/// 将日志逐行写入文件或标准输出，日志订阅关闭后返回
+async fn write_logs(mut rx: mpsc::UnboundedReceiver<LogEntry>, log_file: Option<PathBuf>) {
+    let mut file = match log_file {
+        Some(path) => {
+            match tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!("打开日志文件 {} 失败，改为输出到标准输出: {}", path.display(), e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    while let Some(entry) = rx.recv().await {
+        write_entry(&mut file, &entry).await;
+    }
+    if let Some(ref mut file) = file {
+        let _ = file.flush().await;
+    }
+}
+
+/// 写入单条日志
+async fn write_entry(file: &mut Option<tokio::fs::File>, entry: &LogEntry) {
+    let line = format_entry(entry);
+    match file {
+        Some(file) => {
+            if let Err(e) = file.write_all(format!("{}\n", line).as_bytes()).await {
+                eprintln!("写入日志文件失败: {}", e);
+            }
+        }
+        None => println!("{}", line),
+    }
+}
+
+/// 日志行格式：`时间 [分类] 内容`
+fn format_entry(entry: &LogEntry) -> String {
+    let time = chrono::DateTime::from_timestamp_millis(entry.timestamp)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    format!("{} [{}] {}", time, entry.category, entry.content)
+}
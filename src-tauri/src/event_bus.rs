@@ -0,0 +1,258 @@
+use crate::config::{LogEntry, LogLevel};
+use crate::log_store::LogStore;
+use crate::onebot::OneBotEvent;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// 事件总线默认容量，订阅者落后超过此数量的事件会被丢弃
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// 订阅者过滤函数
+type EventFilter = Box<dyn Fn(&OneBotEvent) -> bool + Send + Sync>;
+
+/// 订阅者统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriberStats {
+    pub name: String,
+    pub received: u64, // 通过过滤并交给订阅者的事件数
+    pub lagged: u64,   // 因处理过慢被丢弃的事件数
+}
+
+/// 按类型统计的事件数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventCounts {
+    pub message: u64,
+    pub message_sent: u64,
+    pub notice: u64,
+    pub request: u64,
+    pub meta_event: u64,
+    pub last_event_time: Option<i64>, // 最近一次事件的时间（秒）
+}
+
+/// 事件统计，由服务器的 "stats" 订阅者更新
+#[derive(Default)]
+pub struct EventStats {
+    message: AtomicU64,
+    message_sent: AtomicU64,
+    notice: AtomicU64,
+    request: AtomicU64,
+    meta_event: AtomicU64,
+    last_event_time: AtomicI64,
+}
+
+impl EventStats {
+    /// 记录一个事件
+    pub fn record(&self, event: &OneBotEvent) {
+        let (counter, time) = match event {
+            OneBotEvent::Message { time, .. } => (&self.message, time),
+            OneBotEvent::MessageSent { time, .. } => (&self.message_sent, time),
+            OneBotEvent::Notice { time, .. } => (&self.notice, time),
+            OneBotEvent::Request { time, .. } => (&self.request, time),
+            OneBotEvent::MetaEvent { time, .. } => (&self.meta_event, time),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.last_event_time.fetch_max(*time, Ordering::Relaxed);
+    }
+
+    /// 当前统计
+    pub fn counts(&self) -> EventCounts {
+        let last_event_time = self.last_event_time.load(Ordering::Relaxed);
+        EventCounts {
+            message: self.message.load(Ordering::Relaxed),
+            message_sent: self.message_sent.load(Ordering::Relaxed),
+            notice: self.notice.load(Ordering::Relaxed),
+            request: self.request.load(Ordering::Relaxed),
+            meta_event: self.meta_event.load(Ordering::Relaxed),
+            last_event_time: (last_event_time > 0).then_some(last_event_time),
+        }
+    }
+}
+
+/// 订阅者计数器
+struct SubscriberCounters {
+    name: String,
+    received: AtomicU64,
+    lagged: AtomicU64,
+}
+
+/// OneBot 事件总线
+///
+/// 服务器收到的事件广播给所有订阅者，每个订阅者有独立的接收队列与过滤条件，
+/// 处理过慢的订阅者只会丢弃自己的事件，不影响其他订阅者。
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<OneBotEvent>>,
+    subscribers: Arc<std::sync::Mutex<Vec<Arc<SubscriberCounters>>>>,
+    /// 订阅者丢弃事件时写入的日志
+    logs: Arc<LogStore>,
+}
+
+impl EventBus {
+    /// 创建指定容量的事件总线
+    pub fn new(capacity: usize, logs: Arc<LogStore>) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender,
+            subscribers: Arc::new(std::sync::Mutex::new(Vec::new())),
+            logs,
+        }
+    }
+
+    /// 发布事件，没有订阅者时直接丢弃
+    pub fn publish(&self, event: OneBotEvent) {
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    /// 订阅事件，只接收满足过滤条件的事件
+    pub fn subscribe<F>(&self, name: &str, filter: F) -> EventSubscription
+    where
+        F: Fn(&OneBotEvent) -> bool + Send + Sync + 'static,
+    {
+        let counters = Arc::new(SubscriberCounters {
+            name: name.to_string(),
+            received: AtomicU64::new(0),
+            lagged: AtomicU64::new(0),
+        });
+        self.subscribers.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::clone(&counters));
+
+        EventSubscription {
+            receiver: self.sender.subscribe(),
+            filter: Box::new(filter),
+            counters,
+            subscribers: Arc::clone(&self.subscribers),
+            logs: Arc::clone(&self.logs),
+        }
+    }
+
+    /// 获取所有订阅者的统计信息
+    pub fn subscriber_stats(&self) -> Vec<SubscriberStats> {
+        self.subscribers.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|counters| SubscriberStats {
+                name: counters.name.clone(),
+                received: counters.received.load(Ordering::Relaxed),
+                lagged: counters.lagged.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// 事件总线上的一个订阅
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<Arc<OneBotEvent>>,
+    filter: EventFilter,
+    counters: Arc<SubscriberCounters>,
+    subscribers: Arc<std::sync::Mutex<Vec<Arc<SubscriberCounters>>>>,
+    logs: Arc<LogStore>,
+}
+
+impl EventSubscription {
+    /// 接收下一个满足过滤条件的事件，事件总线关闭后返回 `None`
+    pub async fn recv(&mut self) -> Option<Arc<OneBotEvent>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => {
+                    if (self.filter)(&event) {
+                        self.counters.received.fetch_add(1, Ordering::Relaxed);
+                        return Some(event);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    self.counters.lagged.fetch_add(skipped, Ordering::Relaxed);
+                    eprintln!("事件订阅者 {} 处理过慢，丢弃了 {} 个事件", self.counters.name, skipped);
+                    self.logs.add(LogEntry::new(
+                        LogLevel::Warning,
+                        "server".to_string(),
+                        format!("[WARN] 事件订阅者 {} 处理过慢，丢弃了 {} 个事件", self.counters.name, skipped),
+                        None,
+                    ));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 在后台任务中按顺序处理事件，直到事件总线关闭
+    pub fn spawn<F, Fut>(mut self, mut handler: F) -> JoinHandle<()>
+    where
+        F: FnMut(Arc<OneBotEvent>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        tokio::spawn(async move {
+            while let Some(event) = self.recv().await {
+                handler(event).await;
+            }
+        })
+    }
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.subscribers.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|counters| !Arc::ptr_eq(counters, &self.counters));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heartbeat(time: i64) -> OneBotEvent {
+        serde_json::from_value(serde_json::json!({
+            "post_type": "meta_event",
+            "time": time,
+            "self_id": 10001,
+            "meta_event_type": "heartbeat",
+            "interval": 5000,
+        })).unwrap()
+    }
+
+    #[test]
+    fn event_stats_count_by_type() {
+        let stats = EventStats::default();
+        assert!(stats.counts().last_event_time.is_none());
+
+        stats.record(&heartbeat(200));
+        stats.record(&heartbeat(100));
+        let counts = stats.counts();
+        assert_eq!(counts.meta_event, 2);
+        assert_eq!(counts.message, 0);
+        assert_eq!(counts.last_event_time, Some(200));
+    }
+
+    #[tokio::test]
+    async fn slow_subscriber_keeps_order_and_reports_lag() {
+        let logs = Arc::new(LogStore::default());
+        let bus = EventBus::new(2, Arc::clone(&logs));
+        let mut subscription = bus.subscribe("slow", |_| true);
+
+        for time in 1..=5 {
+            bus.publish(heartbeat(time));
+        }
+
+        // 容量为 2，最早的 3 个事件被丢弃，剩余事件按发布顺序到达
+        let mut times = Vec::new();
+        for _ in 0..2 {
+            match &*subscription.recv().await.unwrap() {
+                OneBotEvent::MetaEvent { time, .. } => times.push(*time),
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert_eq!(times, vec![4, 5]);
+
+        let stats = bus.subscriber_stats();
+        assert_eq!(stats[0].name, "slow");
+        assert_eq!(stats[0].received, 2);
+        assert_eq!(stats[0].lagged, 3);
+        assert!(logs.history().iter().any(|entry| entry.content.contains("丢弃了 3 个事件")));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::event_bus::EventBus;
+use crate::log_store::LogStore;
+use crate::onebot::{format_event_log, OneBotEvent, OneBotVersion, QuickOperation};
+use crate::onebot_v12::StringIdMap;
+use crate::websocket_server::check_address;
+use crate::config::{LogEntry, LogLevel};
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
+use sha1::Sha1;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+
+/// 快速操作处理函数：根据事件给出需要在响应体中返回的快速操作
+pub type QuickOperationHandler = Arc<dyn Fn(OneBotEvent) -> Pin<Box<dyn Future<Output = Option<QuickOperation>> + Send>> + Send + Sync>;
+
+/// 共享的快速操作处理函数
+type SharedQuickOperationHandler = Arc<Mutex<Option<QuickOperationHandler>>>;
+
+/// 等待快速操作的最长时间，超时后返回空响应，避免 OneBot 实现的上报请求超时
+const QUICK_OPERATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 上报来源的访问限制
+#[derive(Debug, Clone, Default)]
+pub struct AccessRules {
+    /// 允许上报的 IP 段，为空时不限制
+    pub allowlist: Vec<IpNet>,
+    /// 拒绝上报的 IP 段
+    pub denylist: Vec<IpNet>,
+    /// 同时处理的上报请求数上限
+    pub max_connections: Option<u32>,
+}
+
+/// HTTP POST 上报处理所需的共享状态
+#[derive(Clone)]
+struct HttpPostState {
+    secret: Option<String>,
+    allowlist: Arc<Vec<IpNet>>,
+    denylist: Arc<Vec<IpNet>>,
+    max_connections: Option<u32>,
+    slots: Option<Arc<Semaphore>>,
+    event_bus: EventBus,
+    logs: Arc<LogStore>,
+    quick_operation_handler: SharedQuickOperationHandler,
+    string_ids: Arc<StringIdMap>,
+}
+
+/// 创建接收事件上报的路由，只有发往 `path` 的 POST 请求视为事件上报
+///
+/// 处理函数需要来源地址，服务时需使用 `into_make_service_with_connect_info::<SocketAddr>()`。
+pub fn router(
+    secret: Option<String>,
+    event_bus: EventBus,
+    logs: Arc<LogStore>,
+    quick_operation_handler: SharedQuickOperationHandler,
+    string_ids: Arc<StringIdMap>,
+    path: &str,
+    rules: AccessRules,
+) -> Router {
+    let state = HttpPostState {
+        secret: secret.filter(|secret| !secret.is_empty()),
+        allowlist: Arc::new(rules.allowlist),
+        denylist: Arc::new(rules.denylist),
+        max_connections: rules.max_connections,
+        slots: rules.max_connections.map(|max| Arc::new(Semaphore::new(max as usize))),
+        event_bus,
+        logs,
+        quick_operation_handler,
+        string_ids,
+    };
+
+    Router::new()
+        .route(path, post(handle_event_post))
+        .with_state(state)
+}
+
+/// 处理一次事件上报
+async fn handle_event_post(
+    State(state): State<HttpPostState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    // 先检查来源地址与并发上限，与反向 WebSocket 的连接限制一致
+    if let Err(reason) = check_address(addr.ip(), &state.allowlist, &state.denylist) {
+        log_rejection(&state.logs, addr, &reason);
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let _permit = match state.slots.as_ref().map(|slots| Arc::clone(slots).try_acquire_owned()) {
+        Some(Err(_)) => {
+            log_rejection(&state.logs, addr, &format!("并发上报数已达上限 {}", state.max_connections.unwrap_or_default()));
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        Some(Ok(permit)) => Some(permit),
+        None => None,
+    };
+
+    // 配置了 secret 时校验 X-Signature 签名
+    if let Some(secret) = state.secret.as_deref() {
+        let signature = headers.get("X-Signature").and_then(|v| v.to_str().ok());
+        if !verify_signature(secret, &body, signature) {
+            println!("拒绝 OneBot 上报: 签名校验失败");
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
+    // v12 上报转换为 v11 事件；快速操作仅 v11 支持
+    let text = String::from_utf8_lossy(&body);
+    let (event, version) = match crate::onebot_v12::parse_event(&text, None, &state.string_ids) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("[ERROR] 无法解析OneBot上报: {}", e);
+            state.logs.record_unknown_frame("HTTP POST", &text, &e);
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    println!("{}", format_event_log(&event));
+
+    // 先取得快速操作，再发布到事件总线，保证订阅者拿到的事件与插件看到的一致
+    let handler = state.quick_operation_handler.lock().await.clone();
+    let quick_operation = match handler {
+        Some(handler) if version == OneBotVersion::V11 => tokio::time::timeout(QUICK_OPERATION_TIMEOUT, handler(event.clone()))
+            .await
+            .unwrap_or_else(|_| {
+                eprintln!("生成快速操作超时，已忽略");
+                None
+            }),
+        _ => None,
+    };
+
+    state.event_bus.publish(event);
+
+    match quick_operation {
+        Some(operation) if !operation.is_empty() => Json(operation).into_response(),
+        _ => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// 记录被拒绝的上报
+fn log_rejection(logs: &LogStore, addr: SocketAddr, reason: &str) {
+    println!("拒绝 OneBot 上报 ({}): {}", addr, reason);
+    logs.add(LogEntry::new(
+        LogLevel::Warning,
+        "server".to_string(),
+        format!("[WARN] 拒绝 OneBot 上报 ({}): {}", addr, reason),
+        None,
+    ));
+}
+
+/// 校验 `X-Signature: sha1=<hex>` 签名（HMAC-SHA1，密钥为 secret）
+pub fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|value| value.trim().strip_prefix("sha1="))
+        .and_then(|hex_value| hex::decode(hex_value).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus;
+    use crate::onebot::QuickOperation;
+    use serde_json::json;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha1={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn valid_signature_is_accepted() {
+        let body = br#"{"post_type":"meta_event"}"#;
+        assert!(verify_signature("secret", body, Some(&sign("secret", body))));
+    }
+
+    #[test]
+    fn wrong_signature_is_rejected() {
+        let body = br#"{"post_type":"meta_event"}"#;
+        assert!(!verify_signature("secret", body, Some(&sign("other", body))));
+        assert!(!verify_signature("secret", b"tampered", Some(&sign("secret", body))));
+    }
+
+    #[test]
+    fn missing_signature_is_rejected() {
+        assert!(!verify_signature("secret", b"{}", None));
+    }
+
+    #[test]
+    fn malformed_signature_is_rejected() {
+        assert!(!verify_signature("secret", b"{}", Some("sha1=zz")));
+        assert!(!verify_signature("secret", b"{}", Some("sha1=abc")));
+        assert!(!verify_signature("secret", b"{}", Some(&sign("secret", b"{}").replace("sha1=", "md5="))));
+    }
+
+    /// 在本地端口上启动上报路由，返回上报地址的前缀
+    async fn serve(handler: Option<QuickOperationHandler>) -> String {
+        serve_with_rules(handler, AccessRules::default(), Arc::new(LogStore::default())).await
+    }
+
+    async fn serve_with_rules(handler: Option<QuickOperationHandler>, rules: AccessRules, logs: Arc<LogStore>) -> String {
+        let app = router(
+            Some("secret".to_string()),
+            EventBus::new(event_bus::DEFAULT_CAPACITY, Arc::clone(&logs)),
+            logs,
+            Arc::new(Mutex::new(handler)),
+            Arc::new(StringIdMap::default()),
+            "/onebot",
+            rules,
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await });
+        format!("http://{}", addr)
+    }
+
+    fn group_message() -> Vec<u8> {
+        json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "message",
+            "message_type": "group",
+            "sub_type": "normal",
+            "message_id": 1,
+            "group_id": 20002,
+            "user_id": 30003,
+            "message": "ping",
+            "raw_message": "ping",
+            "font": 0,
+            "sender": { "user_id": 30003, "nickname": "tester" },
+        }).to_string().into_bytes()
+    }
+
+    #[tokio::test]
+    async fn quick_operation_reply_is_returned_in_response_body() {
+        let handler: QuickOperationHandler = Arc::new(|event| Box::pin(async move {
+            assert_eq!(event.self_id(), 10001);
+            Some(QuickOperation {
+                reply: Some(json!("pong")),
+                ..QuickOperation::default()
+            })
+        }));
+        let base = serve(Some(handler)).await;
+        let body = group_message();
+        let client = reqwest::Client::new();
+
+        let started = std::time::Instant::now();
+        let response = client.post(format!("{}/onebot", base))
+            .header("X-Signature", sign("secret", &body))
+            .body(body.clone())
+            .send().await.unwrap();
+        assert!(started.elapsed() < QUICK_OPERATION_TIMEOUT);
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let operation: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(operation, json!({ "reply": "pong" }));
+
+        let response = client.post(format!("{}/onebot", base))
+            .header("X-Signature", sign("other", &body))
+            .body(body)
+            .send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn only_configured_path_accepts_posts() {
+        let base = serve(None).await;
+        let body = group_message();
+        let client = reqwest::Client::new();
+
+        let response = client.post(format!("{}/onebot", base))
+            .header("X-Signature", sign("secret", &body))
+            .body(body.clone())
+            .send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let response = client.post(format!("{}/other", base))
+            .header("X-Signature", sign("secret", &body))
+            .body(body)
+            .send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let response = client.get(format!("{}/onebot", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn denied_address_gets_forbidden() {
+        let logs = Arc::new(LogStore::default());
+        let rules = AccessRules {
+            denylist: crate::websocket_server::parse_cidrs(&["127.0.0.0/8".to_string()]).unwrap(),
+            ..AccessRules::default()
+        };
+        let base = serve_with_rules(None, rules, Arc::clone(&logs)).await;
+        let body = group_message();
+
+        let response = reqwest::Client::new().post(format!("{}/onebot", base))
+            .header("X-Signature", sign("secret", &body))
+            .body(body)
+            .send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+        assert!(logs.history().iter().any(|entry| entry.content.contains("命中拒绝列表 127.0.0.0/8")));
+
+        let rules = AccessRules {
+            allowlist: crate::websocket_server::parse_cidrs(&["10.0.0.0/8".to_string()]).unwrap(),
+            ..AccessRules::default()
+        };
+        let base = serve_with_rules(None, rules, Arc::new(LogStore::default())).await;
+        let response = reqwest::Client::new().post(format!("{}/onebot", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn concurrent_posts_over_limit_are_rejected() {
+        let entered = Arc::new(tokio::sync::Notify::new());
+        let release = Arc::new(tokio::sync::Notify::new());
+        let handler: QuickOperationHandler = Arc::new({
+            let (entered, release) = (Arc::clone(&entered), Arc::clone(&release));
+            move |_| {
+                let (entered, release) = (Arc::clone(&entered), Arc::clone(&release));
+                Box::pin(async move {
+                    entered.notify_one();
+                    release.notified().await;
+                    None
+                })
+            }
+        });
+        let rules = AccessRules { max_connections: Some(1), ..AccessRules::default() };
+        let base = serve_with_rules(Some(handler), rules, Arc::new(LogStore::default())).await;
+        let body = group_message();
+        let client = reqwest::Client::new();
+        let post = || client.post(format!("{}/onebot", base))
+            .header("X-Signature", sign("secret", &body))
+            .body(body.clone());
+
+        // 第一个上报停在快速操作处理中，占用唯一的名额
+        let first = tokio::spawn({
+            let request = post();
+            async move { request.send().await.unwrap().status() }
+        });
+        tokio::time::timeout(Duration::from_secs(5), entered.notified()).await.unwrap();
+
+        let response = post().send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+        release.notify_one();
+        assert_eq!(first.await.unwrap(), reqwest::StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,543 @@
+use crate::config::{LogEntry, LogLevel, LogStorageSettings};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+/// 分段文件扩展名，文件名为分段中第一条日志的时间戳（毫秒）
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// 两次清理过期分段之间的最短间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 每页最多返回的日志条数
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// 日志查询条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    pub level: Option<LogLevel>,
+    pub category: Option<String>,
+    pub group_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub start_time: Option<i64>, // 起始时间（毫秒，含）
+    pub end_time: Option<i64>,   // 结束时间（毫秒，含）
+    pub text: Option<String>,    // 在内容与发送者名称中查找，不区分大小写
+    pub page: usize,             // 页码，从 1 开始
+    pub page_size: usize,
+}
+
+impl Default for LogQuery {
+    fn default() -> Self {
+        Self {
+            level: None,
+            category: None,
+            group_id: None,
+            user_id: None,
+            start_time: None,
+            end_time: None,
+            text: None,
+            page: 1,
+            page_size: 100,
+        }
+    }
+}
+
+impl LogQuery {
+    fn matches(&self, entry: &LogEntry, text: Option<&str>) -> bool {
+        self.level.as_ref().is_none_or(|level| &entry.level == level)
+            && self.category.as_deref().is_none_or(|category| entry.category == category)
+            && self.group_id.is_none_or(|group_id| entry.group_id == Some(group_id))
+            && self.user_id.is_none_or(|user_id| entry.user_id == Some(user_id))
+            && self.start_time.is_none_or(|start| entry.timestamp >= start)
+            && self.end_time.is_none_or(|end| entry.timestamp <= end)
+            && text.is_none_or(|text| {
+                entry.content.to_lowercase().contains(text)
+                    || entry.sender_name.as_deref().is_some_and(|name| name.to_lowercase().contains(text))
+            })
+    }
+}
+
+/// 日志查询结果，日志按时间从新到旧排列
+///
+/// 扫描取满当前页后即停止，不统计满足条件的日志总数；按 `page` 向后翻页，直到 `has_more` 为 false。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogQueryResult {
+    pub entries: Vec<LogEntry>,
+    pub has_more: bool,  // 是否还有下一页
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// 持久化的日志存储
+///
+/// 日志由后台线程订阅日志缓冲区后追加写入分段文件，写满后切换到新分段，
+/// 超过保留天数或总大小上限的旧分段会被删除。
+pub struct LogStorage {
+    dir: PathBuf,
+    writer: Arc<Mutex<SegmentWriter>>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl LogStorage {
+    /// 打开日志目录并启动写入线程，线程在应用状态释放后退出
+    pub fn open(state: &Arc<AppState>, dir: PathBuf) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("创建日志目录失败: {}", e))?;
+
+        let rx = state.logs.subscribe();
+        let writer = Arc::new(Mutex::new(SegmentWriter::new(dir.clone())));
+        let state = Arc::downgrade(state);
+        let thread = {
+            let writer = Arc::clone(&writer);
+            std::thread::Builder::new()
+                .name("log-storage".to_string())
+                .spawn(move || SegmentWriter::run(&writer, rx, state))
+                .map_err(|e| format!("启动日志写入线程失败: {}", e))?
+        };
+
+        Ok(Self { dir, writer, thread })
+    }
+
+    /// 等待写入线程写完剩余日志，需先关闭日志缓冲区的订阅
+    pub fn close(self) {
+        if self.thread.join().is_err() {
+            eprintln!("日志写入线程异常退出");
+        }
+    }
+
+    /// 日志目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 返回删除所有分段的任务，任务会阻塞，需在阻塞线程中执行
+    ///
+    /// 任务执行时暂停写入线程，删除后尚未写入的旧日志会被丢弃，之后的日志写入新分段。
+    pub fn purge_task(&self) -> impl FnOnce() -> Result<usize, String> + Send + 'static {
+        let writer = Arc::clone(&self.writer);
+        move || writer.lock().unwrap_or_else(|e| e.into_inner()).purge()
+    }
+
+    /// 读取最近的日志，按时间从旧到新排列，用于启动时恢复日志缓冲区
+    pub fn load_recent(&self, limit: usize) -> Vec<LogEntry> {
+        let query = LogQuery::default();
+        let mut entries = scan(&self.dir, &query, 0, limit).entries;
+        entries.reverse();
+        entries
+    }
+}
+
+/// 查询日志目录中的日志
+pub fn query(dir: &Path, query: &LogQuery) -> LogQueryResult {
+    let page = query.page.max(1);
+    let page_size = query.page_size.clamp(1, MAX_PAGE_SIZE);
+    let scanned = scan(dir, query, (page - 1) * page_size, page_size);
+
+    LogQueryResult {
+        entries: scanned.entries,
+        has_more: scanned.has_more,
+        page,
+        page_size,
+    }
+}
+
+/// 一次扫描的结果
+struct Scanned {
+    entries: Vec<LogEntry>,
+    has_more: bool,
+}
+
+/// 从新到旧扫描分段，跳过前 `offset` 条满足条件的日志后最多取 `limit` 条
+///
+/// 取满后再遇到一条满足条件的日志即停止，不再读取更旧的分段。
+fn scan(dir: &Path, query: &LogQuery, offset: usize, limit: usize) -> Scanned {
+    let text = query.text.as_deref()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_lowercase);
+
+    let segments = list_segments(dir);
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+
+    for (index, (start, path)) in segments.iter().enumerate().rev() {
+        // 分段覆盖 [本分段起始时间, 下一分段起始时间)，与查询时间范围不相交时跳过
+        if query.end_time.is_some_and(|end| *start > end) {
+            continue;
+        }
+        let next_start = segments.get(index + 1).map(|(start, _)| *start);
+        if let (Some(begin), Some(next_start)) = (query.start_time, next_start) {
+            if next_start < begin {
+                break;
+            }
+        }
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("读取日志分段 {} 失败: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let mut matched: Vec<LogEntry> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<LogEntry>(&line).ok())
+            .filter(|entry| query.matches(entry, text.as_deref()))
+            .collect();
+        matched.reverse();
+
+        for entry in matched {
+            if entries.len() >= limit {
+                return Scanned { entries, has_more: true };
+            }
+            if skipped < offset {
+                skipped += 1;
+            } else {
+                entries.push(entry);
+            }
+        }
+    }
+
+    Scanned { entries, has_more: false }
+}
+
+/// 列出日志目录中的分段，按起始时间从旧到新排列
+fn list_segments(dir: &Path) -> Vec<(i64, PathBuf)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut segments: Vec<(i64, PathBuf)> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == SEGMENT_EXTENSION))
+        .filter_map(|path| {
+            let start = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((start, path))
+        })
+        .collect();
+    segments.sort_by_key(|(start, _)| *start);
+    segments
+}
+
+/// 正在写入的分段
+struct Segment {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+/// 分段写入器，运行在独立线程中
+struct SegmentWriter {
+    dir: PathBuf,
+    current: Option<Segment>,
+    last_cleanup: Option<Instant>,
+    /// 上次清空的时间（毫秒），早于该时间的日志不再写入
+    purged_at: Option<i64>,
+}
+
+impl SegmentWriter {
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            current: None,
+            last_cleanup: None,
+            purged_at: None,
+        }
+    }
+
+    /// 逐批写入日志，每批写完后刷新到磁盘
+    fn run(writer: &Mutex<Self>, mut rx: mpsc::UnboundedReceiver<LogEntry>, state: Weak<AppState>) {
+        while let Some(entry) = rx.blocking_recv() {
+            let Some(settings) = state.upgrade().map(|state| state.settings().log_storage.clone()) else {
+                break;
+            };
+
+            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+            if !settings.enabled {
+                writer.current = None;
+                continue;
+            }
+
+            writer.write(&entry, &settings);
+            while let Ok(entry) = rx.try_recv() {
+                writer.write(&entry, &settings);
+            }
+
+            if let Some(ref mut segment) = writer.current {
+                if let Err(e) = segment.writer.flush() {
+                    eprintln!("写入日志分段失败: {}", e);
+                }
+            }
+
+            if writer.last_cleanup.is_none_or(|last| last.elapsed() >= CLEANUP_INTERVAL) {
+                writer.cleanup(&settings);
+            }
+        }
+    }
+
+    /// 关闭当前分段并删除所有分段，返回删除的分段数
+    fn purge(&mut self) -> Result<usize, String> {
+        self.current = None;
+        self.purged_at = Some(chrono::Utc::now().timestamp_millis());
+
+        let mut removed = 0;
+        for (_, path) in list_segments(&self.dir) {
+            fs::remove_file(&path)
+                .map_err(|e| format!("删除日志分段 {} 失败: {}", path.display(), e))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    fn write(&mut self, entry: &LogEntry, settings: &LogStorageSettings) {
+        if self.purged_at.is_some_and(|purged_at| entry.timestamp < purged_at) {
+            return;
+        }
+
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line + "\n",
+            Err(e) => {
+                eprintln!("序列化日志失败: {}", e);
+                return;
+            }
+        };
+
+        let max_segment_bytes = settings.max_segment_mb.max(1) * 1024 * 1024;
+        if self.current.as_ref().is_some_and(|segment| segment.size >= max_segment_bytes) {
+            self.current = None;
+            self.cleanup(settings);
+        }
+
+        if self.current.is_none() {
+            self.current = self.open_segment(entry.timestamp, max_segment_bytes);
+        }
+
+        let Some(ref mut segment) = self.current else {
+            return;
+        };
+        match segment.writer.write_all(line.as_bytes()) {
+            Ok(()) => segment.size += line.len() as u64,
+            Err(e) => {
+                eprintln!("写入日志分段 {} 失败: {}", segment.path.display(), e);
+                self.current = None;
+            }
+        }
+    }
+
+    /// 打开要写入的分段：最新分段未写满时继续追加，否则以当前日志时间创建新分段
+    fn open_segment(&self, timestamp: i64, max_segment_bytes: u64) -> Option<Segment> {
+        let latest = list_segments(&self.dir).pop()
+            .and_then(|(_, path)| Some((fs::metadata(&path).ok()?.len(), path)))
+            .filter(|(size, _)| *size < max_segment_bytes);
+
+        let (path, size) = match latest {
+            Some((size, path)) => (path, size),
+            None => (self.dir.join(format!("{}.{}", timestamp, SEGMENT_EXTENSION)), 0),
+        };
+
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => Some(Segment {
+                path,
+                writer: BufWriter::new(file),
+                size,
+            }),
+            Err(e) => {
+                eprintln!("打开日志分段 {} 失败: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// 删除超过保留天数的分段，再从最旧的分段开始删除直到总大小不超过上限
+    fn cleanup(&mut self, settings: &LogStorageSettings) {
+        self.last_cleanup = Some(Instant::now());
+
+        let current = self.current.as_ref().map(|segment| segment.path.clone());
+        let mut segments: Vec<(PathBuf, u64, Option<SystemTime>)> = list_segments(&self.dir)
+            .into_iter()
+            .filter(|(_, path)| Some(path) != current.as_ref())
+            .filter_map(|(_, path)| {
+                let metadata = fs::metadata(&path).ok()?;
+                Some((path, metadata.len(), metadata.modified().ok()))
+            })
+            .collect();
+
+        if settings.retention_days > 0 {
+            let retention = Duration::from_secs(settings.retention_days * 24 * 60 * 60);
+            segments.retain(|(path, _, modified)| {
+                let expired = modified
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age > retention);
+                if expired {
+                    remove_segment(path);
+                }
+                !expired
+            });
+        }
+
+        let current_size = self.current.as_ref().map_or(0, |segment| segment.size);
+        let max_total_bytes = settings.max_total_mb.max(1) * 1024 * 1024;
+        let mut total: u64 = current_size + segments.iter().map(|(_, size, _)| size).sum::<u64>();
+        for (path, size, _) in segments {
+            if total <= max_total_bytes {
+                break;
+            }
+            remove_segment(&path);
+            total -= size;
+        }
+    }
+}
+
+fn remove_segment(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        eprintln!("删除日志分段 {} 失败: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: i64, level: LogLevel, content: &str) -> LogEntry {
+        let mut entry = LogEntry::new(level, "test".to_string(), content.to_string(), None);
+        entry.timestamp = timestamp;
+        entry
+    }
+
+    /// 直接写入一个分段文件
+    fn write_segment(dir: &Path, entries: &[LogEntry]) -> PathBuf {
+        let path = dir.join(format!("{}.{}", entries[0].timestamp, SEGMENT_EXTENSION));
+        let lines: String = entries.iter()
+            .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+            .collect();
+        fs::write(&path, lines).unwrap();
+        path
+    }
+
+    fn contents(entries: &[LogEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.content.as_str()).collect()
+    }
+
+    #[test]
+    fn writer_rotates_segments_at_max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = LogStorageSettings {
+            max_segment_mb: 1,
+            max_total_mb: 100,
+            ..LogStorageSettings::default()
+        };
+
+        // 每条约 64 KB，20 条写满一个 1 MB 的分段
+        let content = "x".repeat(64 * 1024);
+        let mut writer = SegmentWriter::new(dir.path().to_path_buf());
+        for i in 0..40 {
+            writer.write(&entry(i * 1000, LogLevel::Info, &content), &settings);
+        }
+        writer.current = None;
+
+        let segments = list_segments(dir.path());
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].0, 0);
+        for (_, path) in &segments[..2] {
+            let size = fs::metadata(path).unwrap().len();
+            assert!((1024 * 1024..1024 * 1024 + 70 * 1024).contains(&size), "{}", size);
+        }
+    }
+
+    #[test]
+    fn cleanup_removes_expired_and_oversized_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "x".repeat(600 * 1024);
+        let paths: Vec<PathBuf> = (1..=4)
+            .map(|i| write_segment(dir.path(), &[entry(i * 1000, LogLevel::Info, &content)]))
+            .collect();
+
+        // 最旧的分段超过保留天数
+        let old = SystemTime::now() - Duration::from_secs(3 * 24 * 60 * 60);
+        File::options().write(true).open(&paths[0]).unwrap().set_modified(old).unwrap();
+
+        let settings = LogStorageSettings {
+            max_total_mb: 1,
+            retention_days: 2,
+            ..LogStorageSettings::default()
+        };
+        SegmentWriter::new(dir.path().to_path_buf()).cleanup(&settings);
+
+        // 按时间删除第一个，再从旧到新删除直到总大小不超过 1 MB
+        let remaining: Vec<PathBuf> = list_segments(dir.path()).into_iter().map(|(_, path)| path).collect();
+        assert_eq!(remaining, paths[3..].to_vec());
+    }
+
+    #[test]
+    fn query_filters_and_paginates_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        write_segment(dir.path(), &[
+            entry(1000, LogLevel::Info, "a1"),
+            entry(1001, LogLevel::Error, "a2 超时"),
+            entry(1002, LogLevel::Info, "a3"),
+        ]);
+        write_segment(dir.path(), &[
+            entry(2000, LogLevel::Error, "b1"),
+            entry(2001, LogLevel::Info, "b2 超时"),
+        ]);
+        let mut grouped = entry(3000, LogLevel::Info, "c1");
+        grouped.group_id = Some(123);
+        write_segment(dir.path(), &[grouped, entry(3001, LogLevel::Info, "c2")]);
+
+        let page = |page: usize, page_size: usize| LogQuery { page, page_size, ..LogQuery::default() };
+
+        let result = query(dir.path(), &page(1, 3));
+        assert_eq!(contents(&result.entries), ["c2", "c1", "b2 超时"]);
+        assert!(result.has_more);
+
+        let result = query(dir.path(), &page(2, 3));
+        assert_eq!(contents(&result.entries), ["b1", "a3", "a2 超时"]);
+        assert!(result.has_more);
+
+        let result = query(dir.path(), &page(3, 3));
+        assert_eq!(contents(&result.entries), ["a1"]);
+        assert!(!result.has_more);
+
+        // 超出范围的页为空
+        let result = query(dir.path(), &page(4, 3));
+        assert!(result.entries.is_empty());
+        assert!(!result.has_more);
+
+        let result = query(dir.path(), &LogQuery { level: Some(LogLevel::Error), ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["b1", "a2 超时"]);
+
+        let result = query(dir.path(), &LogQuery { text: Some(" 超时 ".to_string()), ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["b2 超时", "a2 超时"]);
+
+        let result = query(dir.path(), &LogQuery { group_id: Some(123), ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["c1"]);
+
+        let result = query(dir.path(), &LogQuery { start_time: Some(1002), end_time: Some(2000), ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["b1", "a3"]);
+    }
+
+    #[test]
+    fn scan_stops_after_page_is_filled() {
+        let dir = tempfile::tempdir().unwrap();
+        write_segment(dir.path(), &[entry(1000, LogLevel::Info, "a1"), entry(1001, LogLevel::Info, "a2")]);
+        write_segment(dir.path(), &[entry(2000, LogLevel::Info, "b1"), entry(2001, LogLevel::Info, "b2")]);
+
+        // 较新的分段已取满一页，不再读取最旧的分段
+        let result = query(dir.path(), &LogQuery { page_size: 1, ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["b2"]);
+        assert!(result.has_more);
+
+        let result = query(dir.path(), &LogQuery { page_size: 4, ..LogQuery::default() });
+        assert_eq!(contents(&result.entries), ["b2", "b1", "a2", "a1"]);
+        assert!(!result.has_more);
+    }
+}
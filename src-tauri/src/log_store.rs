@@ -0,0 +1,182 @@
+use crate::config::{LogEntry, LogLevel};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+/// 未加载配置时的日志条数上限
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// 日志订阅者
+enum Subscriber {
+    /// 不丢弃日志的订阅者，用于日志持久化与本地输出
+    Unbounded(mpsc::UnboundedSender<LogEntry>),
+    /// 有界订阅者，队列已满时丢弃新日志，用于读取速度不受控制的远程客户端
+    Bounded(mpsc::Sender<LogEntry>),
+}
+
+impl Subscriber {
+    fn is_closed(&self) -> bool {
+        match self {
+            Self::Unbounded(tx) => tx.is_closed(),
+            Self::Bounded(tx) => tx.is_closed(),
+        }
+    }
+
+    fn send(&self, entry: LogEntry) {
+        match self {
+            Self::Unbounded(tx) => {
+                if let Err(e) = tx.send(entry) {
+                    eprintln!("发送日志给订阅者失败: {}", e);
+                }
+            }
+            Self::Bounded(tx) => {
+                let _ = tx.try_send(entry);
+            }
+        }
+    }
+}
+
+/// 日志缓冲区与实时日志订阅者
+///
+/// 写入日志是事件处理的热路径，缓冲区与订阅者列表各用一把短暂持有的同步锁，
+/// 条数上限以原子变量保存，写入时不需要读取配置。
+pub struct LogStore {
+    entries: Mutex<VecDeque<LogEntry>>,
+    max_entries: AtomicUsize,
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl LogStore {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            max_entries: AtomicUsize::new(max_entries.max(1)),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 设置缓冲区最多保留的日志条数
+    pub fn set_max_entries(&self, max_entries: usize) {
+        self.max_entries.store(max_entries.max(1), Ordering::Relaxed);
+    }
+
+    /// 添加日志条目到缓冲区并推送给订阅者
+    pub fn add(&self, entry: LogEntry) {
+        {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            let max_entries = self.max_entries.load(Ordering::Relaxed);
+            while entries.len() >= max_entries {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+
+        // 移除已关闭的订阅者
+        subscribers.retain(|tx| !tx.is_closed());
+
+        for subscriber in subscribers.iter() {
+            subscriber.send(entry.clone());
+        }
+    }
+
+    /// 载入持久化的历史日志（按时间从旧到新），放在现有日志之前且不推送给订阅者
+    pub fn preload(&self, history: Vec<LogEntry>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let max_entries = self.max_entries.load(Ordering::Relaxed);
+        for entry in history.into_iter().rev() {
+            if entries.len() >= max_entries {
+                break;
+            }
+            entries.push_front(entry);
+        }
+    }
+
+    /// 记录无法解析的 OneBot 数据，保留原始文本与解析错误以便排查协议差异
+    pub fn record_unknown_frame(&self, source: &str, raw: &str, error: &str) {
+        self.add(LogEntry::new(
+            LogLevel::Warning,
+            "unknown".to_string(),
+            format!("[WARN] 无法解析的 OneBot 数据 ({}): {}", source, error),
+            Some(serde_json::json!({
+                "source": source,
+                "error": error,
+                "raw": raw,
+            })),
+        ));
+    }
+
+    /// 获取缓冲区中的所有日志
+    pub fn history(&self) -> Vec<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().cloned().collect()
+    }
+
+    /// 获取指定分类的日志
+    pub fn by_category(&self, category: &str) -> Vec<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter()
+            .filter(|entry| entry.category == category)
+            .cloned()
+            .collect()
+    }
+
+    /// 清空缓冲区
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// 关闭所有订阅，订阅者收完已写入的日志后结束
+    pub fn close_subscribers(&self) {
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// 订阅之后写入的日志
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<LogEntry> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(Subscriber::Unbounded(tx));
+        rx
+    }
+
+    /// 订阅之后写入的日志，最多缓存 `capacity` 条，读取跟不上时丢弃新日志
+    pub fn subscribe_bounded(&self, capacity: usize) -> mpsc::Receiver<LogEntry> {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(Subscriber::Bounded(tx));
+        rx
+    }
+}
+
+impl Default for LogStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str) -> LogEntry {
+        LogEntry::new(LogLevel::Info, "test".to_string(), content.to_string(), None)
+    }
+
+    #[test]
+    fn bounded_subscriber_drops_entries_when_full() {
+        let store = LogStore::default();
+        let mut bounded = store.subscribe_bounded(2);
+        let mut unbounded = store.subscribe();
+
+        for content in ["a", "b", "c"] {
+            store.add(entry(content));
+        }
+
+        assert_eq!(bounded.try_recv().unwrap().content, "a");
+        assert_eq!(bounded.try_recv().unwrap().content, "b");
+        assert!(bounded.try_recv().is_err());
+        for content in ["a", "b", "c"] {
+            assert_eq!(unbounded.try_recv().unwrap().content, content);
+        }
+    }
+}
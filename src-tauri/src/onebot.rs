@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
 /// OneBot 事件类型
@@ -19,7 +19,7 @@ pub enum OneBotEvent {
         #[serde(skip_serializing_if = "Option::is_none")]
         real_seq: Option<String>,
         user_id: i64,
-        message: serde_json::Value, // 支持数组和字符串格式
+        message: MessageContent, // 支持数组和字符串格式
         raw_message: String,
         font: i32,
         sender: Sender,
@@ -28,29 +28,46 @@ pub enum OneBotEvent {
         #[serde(skip_serializing_if = "Option::is_none")]
         message_format: Option<String>,
     },
-    #[serde(rename = "notice")]
-    Notice {
+    /// 机器人自身发出的消息（NapCat、LLOneBot 等实现会上报其他设备发出的消息）
+    #[serde(rename = "message_sent")]
+    MessageSent {
         time: i64,
         self_id: i64,
-        notice_type: String,
+        message_type: String,
         sub_type: String,
+        message_id: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message_seq: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        real_id: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        real_seq: Option<String>,
         user_id: i64,
+        message: MessageContent,
+        raw_message: String,
+        font: i32,
+        sender: Sender,
         #[serde(skip_serializing_if = "Option::is_none")]
         group_id: Option<i64>,
+        /// 私聊消息的接收者
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_id: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message_format: Option<String>,
+    },
+    #[serde(rename = "notice")]
+    Notice {
+        time: i64,
+        self_id: i64,
         #[serde(flatten)]
-        extra: HashMap<String, serde_json::Value>,
+        notice: NoticeEvent,
     },
     #[serde(rename = "request")]
     Request {
         time: i64,
         self_id: i64,
-        request_type: String,
-        sub_type: String,
-        user_id: i64,
-        comment: String,
-        flag: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        group_id: Option<i64>,
+        #[serde(flatten)]
+        request: RequestEvent,
     },
     #[serde(rename = "meta_event")]
     MetaEvent {
@@ -68,6 +85,257 @@ pub enum OneBotEvent {
     },
 }
 
+impl OneBotEvent {
+    /// 获取事件所属的机器人账号
+    pub fn self_id(&self) -> i64 {
+        match self {
+            OneBotEvent::Message { self_id, .. }
+            | OneBotEvent::MessageSent { self_id, .. }
+            | OneBotEvent::Notice { self_id, .. }
+            | OneBotEvent::Request { self_id, .. }
+            | OneBotEvent::MetaEvent { self_id, .. } => *self_id,
+        }
+    }
+}
+
+/// 通知事件，按 `notice_type` 区分
+///
+/// 未知类型或字段不完整的通知会落入 `Other`，保留原始字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "notice_type", rename_all = "snake_case")]
+pub enum NoticeEvent {
+    /// 群文件上传
+    GroupUpload {
+        group_id: i64,
+        user_id: i64,
+        file: GroupFile,
+    },
+    /// 群管理员变动，sub_type 为 set / unset
+    GroupAdmin {
+        sub_type: String,
+        group_id: i64,
+        user_id: i64,
+    },
+    /// 群成员减少，sub_type 为 leave / kick / kick_me
+    GroupDecrease {
+        sub_type: String,
+        group_id: i64,
+        operator_id: i64,
+        user_id: i64,
+    },
+    /// 群成员增加，sub_type 为 approve / invite
+    GroupIncrease {
+        sub_type: String,
+        group_id: i64,
+        operator_id: i64,
+        user_id: i64,
+    },
+    /// 群禁言，sub_type 为 ban / lift_ban，duration 单位为秒
+    GroupBan {
+        sub_type: String,
+        group_id: i64,
+        operator_id: i64,
+        user_id: i64,
+        duration: i64,
+    },
+    /// 好友添加
+    FriendAdd {
+        user_id: i64,
+    },
+    /// 群消息撤回
+    GroupRecall {
+        group_id: i64,
+        user_id: i64,
+        operator_id: i64,
+        message_id: i64,
+    },
+    /// 好友消息撤回
+    FriendRecall {
+        user_id: i64,
+        message_id: i64,
+    },
+    /// 群精华消息，sub_type 为 add / delete
+    Essence {
+        sub_type: String,
+        group_id: i64,
+        sender_id: i64,
+        operator_id: i64,
+        message_id: i64,
+    },
+    /// 提醒类通知（戳一戳、红包运气王、群荣誉变更）
+    Notify(NotifyEvent),
+    /// 未识别的通知
+    #[serde(untagged)]
+    Other {
+        notice_type: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sub_type: Option<String>,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+}
+
+/// 提醒类通知，按 `sub_type` 区分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "sub_type", rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// 戳一戳，私聊时没有 group_id
+    Poke {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group_id: Option<i64>,
+        user_id: i64,
+        target_id: i64,
+    },
+    /// 群红包运气王
+    LuckyKing {
+        group_id: i64,
+        user_id: i64,
+        target_id: i64,
+    },
+    /// 群荣誉变更，honor_type 为 talkative / performer / emotion
+    Honor {
+        group_id: i64,
+        honor_type: String,
+        user_id: i64,
+    },
+    /// 未识别的提醒
+    #[serde(untagged)]
+    Other {
+        sub_type: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+}
+
+/// 群文件信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupFile {
+    pub id: String,
+    pub name: String,
+    pub size: i64,
+    #[serde(default)]
+    pub busid: i64,
+}
+
+impl NoticeEvent {
+    /// 获取通知类型名称
+    #[allow(dead_code)]
+    pub fn notice_type(&self) -> &str {
+        match self {
+            NoticeEvent::GroupUpload { .. } => "group_upload",
+            NoticeEvent::GroupAdmin { .. } => "group_admin",
+            NoticeEvent::GroupDecrease { .. } => "group_decrease",
+            NoticeEvent::GroupIncrease { .. } => "group_increase",
+            NoticeEvent::GroupBan { .. } => "group_ban",
+            NoticeEvent::FriendAdd { .. } => "friend_add",
+            NoticeEvent::GroupRecall { .. } => "group_recall",
+            NoticeEvent::FriendRecall { .. } => "friend_recall",
+            NoticeEvent::Essence { .. } => "essence",
+            NoticeEvent::Notify(_) => "notify",
+            NoticeEvent::Other { notice_type, .. } => notice_type,
+        }
+    }
+
+    /// 获取通知涉及的用户
+    pub fn user_id(&self) -> Option<i64> {
+        match self {
+            NoticeEvent::GroupUpload { user_id, .. }
+            | NoticeEvent::GroupAdmin { user_id, .. }
+            | NoticeEvent::GroupDecrease { user_id, .. }
+            | NoticeEvent::GroupIncrease { user_id, .. }
+            | NoticeEvent::GroupBan { user_id, .. }
+            | NoticeEvent::FriendAdd { user_id }
+            | NoticeEvent::GroupRecall { user_id, .. }
+            | NoticeEvent::FriendRecall { user_id, .. }
+            | NoticeEvent::Notify(NotifyEvent::Poke { user_id, .. })
+            | NoticeEvent::Notify(NotifyEvent::LuckyKing { user_id, .. })
+            | NoticeEvent::Notify(NotifyEvent::Honor { user_id, .. }) => Some(*user_id),
+            NoticeEvent::Essence { sender_id, .. } => Some(*sender_id),
+            NoticeEvent::Notify(NotifyEvent::Other { extra, .. })
+            | NoticeEvent::Other { extra, .. } => extra.get("user_id").and_then(|v| v.as_i64()),
+        }
+    }
+
+    /// 获取通知所在的群
+    pub fn group_id(&self) -> Option<i64> {
+        match self {
+            NoticeEvent::GroupUpload { group_id, .. }
+            | NoticeEvent::GroupAdmin { group_id, .. }
+            | NoticeEvent::GroupDecrease { group_id, .. }
+            | NoticeEvent::GroupIncrease { group_id, .. }
+            | NoticeEvent::GroupBan { group_id, .. }
+            | NoticeEvent::GroupRecall { group_id, .. }
+            | NoticeEvent::Essence { group_id, .. }
+            | NoticeEvent::Notify(NotifyEvent::LuckyKing { group_id, .. })
+            | NoticeEvent::Notify(NotifyEvent::Honor { group_id, .. }) => Some(*group_id),
+            NoticeEvent::Notify(NotifyEvent::Poke { group_id, .. }) => *group_id,
+            NoticeEvent::FriendAdd { .. } | NoticeEvent::FriendRecall { .. } => None,
+            NoticeEvent::Notify(NotifyEvent::Other { extra, .. })
+            | NoticeEvent::Other { extra, .. } => extra.get("group_id").and_then(|v| v.as_i64()),
+        }
+    }
+}
+
+/// 请求事件，按 `request_type` 区分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request_type", rename_all = "snake_case")]
+pub enum RequestEvent {
+    /// 加好友请求
+    Friend {
+        user_id: i64,
+        #[serde(default)]
+        comment: String,
+        flag: String,
+    },
+    /// 加群请求或邀请入群，sub_type 为 add / invite
+    Group {
+        sub_type: String,
+        group_id: i64,
+        user_id: i64,
+        #[serde(default)]
+        comment: String,
+        flag: String,
+    },
+    /// 未识别的请求
+    #[serde(untagged)]
+    Other {
+        request_type: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sub_type: Option<String>,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+}
+
+impl RequestEvent {
+    /// 获取请求类型名称
+    #[allow(dead_code)]
+    pub fn request_type(&self) -> &str {
+        match self {
+            RequestEvent::Friend { .. } => "friend",
+            RequestEvent::Group { .. } => "group",
+            RequestEvent::Other { request_type, .. } => request_type,
+        }
+    }
+
+    /// 获取发起请求的用户
+    pub fn user_id(&self) -> Option<i64> {
+        match self {
+            RequestEvent::Friend { user_id, .. } | RequestEvent::Group { user_id, .. } => Some(*user_id),
+            RequestEvent::Other { extra, .. } => extra.get("user_id").and_then(|v| v.as_i64()),
+        }
+    }
+
+    /// 获取请求所在的群
+    pub fn group_id(&self) -> Option<i64> {
+        match self {
+            RequestEvent::Group { group_id, .. } => Some(*group_id),
+            RequestEvent::Friend { .. } => None,
+            RequestEvent::Other { extra, .. } => extra.get("group_id").and_then(|v| v.as_i64()),
+        }
+    }
+}
+
 /// OneBot 状态信息（用于心跳包）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OneBotStatus {
@@ -96,12 +364,165 @@ pub struct Sender {
     pub title: Option<String>,
 }
 
-/// 消息段（用于解析消息内容）
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MessageSegment {
-    #[serde(rename = "type")]
-    pub seg_type: String,
-    pub data: HashMap<String, serde_json::Value>,
+/// 消息内容，对应 `message_format` 的 string 与 array 两种格式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// array 格式：消息段数组
+    Segments(Vec<MessageSegment>),
+    /// string 格式：CQ 码字符串
+    Text(String),
+}
+
+/// 消息段
+///
+/// 字段均按 OneBot v11 规范保存为字符串；未列出的字段保存在 `extra` 中，
+/// 未识别或字段不完整的消息段落入 `Other`，保证与 CQ 码互转时不丢失内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum MessageSegment {
+    /// 纯文本
+    Text {
+        text: String,
+    },
+    /// 艾特，qq 为 all 时表示全体成员
+    At {
+        #[serde(deserialize_with = "string_or_number")]
+        qq: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// QQ 表情
+    Face {
+        #[serde(deserialize_with = "string_or_number")]
+        id: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 图片
+    Image {
+        file: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 语音
+    Record {
+        file: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 短视频
+    Video {
+        file: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 回复
+    Reply {
+        #[serde(deserialize_with = "string_or_number")]
+        id: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 合并转发
+    Forward {
+        #[serde(deserialize_with = "string_or_number")]
+        id: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// JSON 卡片消息
+    Json {
+        #[serde(deserialize_with = "string_or_number")]
+        data: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// XML 卡片消息
+    Xml {
+        data: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 文件
+    File {
+        file: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 戳一戳
+    Poke {
+        #[serde(rename = "type", deserialize_with = "string_or_number")]
+        poke_type: String,
+        #[serde(deserialize_with = "string_or_number")]
+        id: String,
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 掷骰子
+    Dice {
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 猜拳
+    Rps {
+        #[serde(flatten)]
+        extra: HashMap<String, serde_json::Value>,
+    },
+    /// 未识别的消息段
+    #[serde(untagged)]
+    Other {
+        #[serde(rename = "type")]
+        seg_type: String,
+        #[serde(default)]
+        data: HashMap<String, serde_json::Value>,
+    },
+}
+
+impl MessageSegment {
+    /// 创建纯文本消息段
+    pub fn text(content: &str) -> Self {
+        MessageSegment::Text { text: content.to_string() }
+    }
+
+    /// 由类型与字符串参数构造消息段，字段不符合已知类型时构造为 `Other`
+    pub fn from_parts(seg_type: &str, data: HashMap<String, String>) -> Self {
+        let data: HashMap<String, serde_json::Value> = data.into_iter()
+            .map(|(key, value)| (key, serde_json::Value::String(value)))
+            .collect();
+        let value = serde_json::json!({ "type": seg_type, "data": data });
+        serde_json::from_value(value).unwrap_or_else(|_| MessageSegment::Other {
+            seg_type: seg_type.to_string(),
+            data,
+        })
+    }
+
+    /// 拆分为类型与字符串参数，非字符串的值按 JSON 文本保存
+    pub fn to_parts(&self) -> (String, HashMap<String, String>) {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        let seg_type = value.get("type").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let data = value.get("data")
+            .and_then(|v| v.as_object())
+            .map(|data| data.iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect())
+            .unwrap_or_default();
+        (seg_type, data)
+    }
+}
+
+/// 兼容部分实现以数字或对象上报本应为字符串的字段
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    })
 }
 
 /// OneBot API 调用请求
@@ -153,7 +574,8 @@ pub struct BotAccount {
     pub status: String,
     pub friends: Vec<Friend>,
     pub groups: Vec<Group>,
-    pub last_updated: i64,
+    pub friends_updated: i64, // 好友列表缓存的更新时间，0 表示需要重新获取
+    pub groups_updated: i64,  // 群聊列表缓存的更新时间，0 表示需要重新获取
 }
 
 /// 机器人登录信息
@@ -199,6 +621,74 @@ pub enum ConnectionStatus {
     Connecting,
 }
 
+/// OneBot 协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OneBotVersion {
+    #[default]
+    V11,
+    V12,
+}
+
+/// OneBot 连接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionMode {
+    /// 反向 WebSocket：本程序监听，OneBot 实现主动连接
+    #[default]
+    ReverseWs,
+    /// 正向 WebSocket：OneBot 实现监听，本程序主动连接
+    ForwardWs,
+    /// HTTP POST：OneBot 实现将事件 POST 到本程序监听的地址
+    HttpPost,
+}
+
+/// HTTP POST 快速操作
+///
+/// 作为事件上报请求的响应体返回，OneBot 实现会据此执行回复、撤回、踢人、禁言、处理请求等操作。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuickOperation {
+    /// 回复内容（消息事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<serde_json::Value>,
+    /// 回复内容是否作为纯文本发送
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_escape: Option<bool>,
+    /// 群聊回复时是否 @ 发送者
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_sender: Option<bool>,
+    /// 撤回该条消息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete: Option<bool>,
+    /// 把发送者踢出群组
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kick: Option<bool>,
+    /// 禁言发送者
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban: Option<bool>,
+    /// 禁言时长（秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ban_duration: Option<i64>,
+    /// 是否同意请求（请求事件）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    /// 添加好友后的备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    /// 拒绝加群的理由
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl QuickOperation {
+    /// 是否不包含任何操作
+    pub fn is_empty(&self) -> bool {
+        serde_json::to_value(self)
+            .map(|value| value.as_object().is_none_or(|fields| fields.is_empty()))
+            .unwrap_or(true)
+    }
+}
+
 /// OneBot 配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OneBotConfig {
@@ -206,6 +696,29 @@ pub struct OneBotConfig {
     pub port: u16,
     pub access_token: Option<String>,
     pub secret: Option<String>,
+    #[serde(default)]
+    pub mode: ConnectionMode,
+    /// 正向 WebSocket 模式下要连接的地址，如 `ws://127.0.0.1:3001`
+    #[serde(default)]
+    pub url: Option<String>,
+    /// HTTP POST 模式下接收上报的路径，为空时为 `/`
+    #[serde(default)]
+    pub post_path: Option<String>,
+    /// 反向 WebSocket 启用 TLS 时的证书链路径（PEM）
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+    /// 反向 WebSocket 启用 TLS 时的私钥路径（PEM）
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+    /// 最大同时连接数，为空或 0 时不限制
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    /// 允许连接的 IP 段（CIDR），为空时允许所有地址
+    #[serde(default)]
+    pub ip_allowlist: Vec<String>,
+    /// 拒绝连接的 IP 段（CIDR），优先于允许列表
+    #[serde(default)]
+    pub ip_denylist: Vec<String>,
 }
 
 impl Default for OneBotConfig {
@@ -215,32 +728,28 @@ impl Default for OneBotConfig {
             port: 8080,
             access_token: None,
             secret: None,
+            mode: ConnectionMode::default(),
+            url: None,
+            post_path: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            max_connections: None,
+            ip_allowlist: Vec::new(),
+            ip_denylist: Vec::new(),
         }
     }
 }
 
 /// 解析消息内容为纯文本
-pub fn extract_plain_text(message: &serde_json::Value) -> String {
+pub fn extract_plain_text(message: &MessageContent) -> String {
     match message {
-        serde_json::Value::String(text) => text.clone(),
-        serde_json::Value::Array(segments) => {
-            let mut text = String::new();
-            for segment in segments {
-                if let Some(seg) = segment.as_object() {
-                    if let Some(seg_type) = seg.get("type").and_then(|v| v.as_str()) {
-                        if seg_type == "text" {
-                            if let Some(data) = seg.get("data").and_then(|v| v.as_object()) {
-                                if let Some(content) = data.get("text").and_then(|v| v.as_str()) {
-                                    text.push_str(content);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            text
-        }
-        _ => message.to_string(),
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::Segments(segments) => segments.iter()
+            .filter_map(|segment| match segment {
+                MessageSegment::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
     }
 }
 
@@ -283,6 +792,22 @@ pub fn format_event_log(event: &OneBotEvent) -> String {
                     message_type, sender_name, sender.user_id, raw_message)
             }
         }
+        OneBotEvent::MessageSent {
+            message_type,
+            group_id,
+            user_id,
+            target_id,
+            message,
+            raw_message,
+            ..
+        } => {
+            let plain_text = extract_plain_text(message);
+            match (message_type.as_str(), group_id) {
+                ("group", Some(gid)) => format!("[INFO] 发送 -> 群聊 [群({})] {}", gid, plain_text),
+                ("private", _) => format!("[INFO] 发送 -> 私聊 [{}] {}", target_id.unwrap_or(*user_id), plain_text),
+                _ => format!("[INFO] 发送 -> {} {}", message_type, raw_message),
+            }
+        }
         OneBotEvent::MetaEvent { meta_event_type, interval, status, .. } => {
             match meta_event_type.as_str() {
                 "heartbeat" => {
@@ -300,11 +825,287 @@ pub fn format_event_log(event: &OneBotEvent) -> String {
                 }
             }
         }
-        OneBotEvent::Notice { notice_type, .. } => {
+        OneBotEvent::Notice { notice, .. } => format_notice_log(notice),
+        OneBotEvent::Request { request, .. } => format_request_log(request),
+    }
+} 
+
+/// 格式化通知事件
+fn format_notice_log(notice: &NoticeEvent) -> String {
+    match notice {
+        NoticeEvent::GroupUpload { group_id, user_id, file } => {
+            format!("[INFO] 群文件上传 [群({})] [{}] {} ({} 字节)", group_id, user_id, file.name, file.size)
+        }
+        NoticeEvent::GroupAdmin { sub_type, group_id, user_id } => {
+            let action = if sub_type == "set" { "设为管理员" } else { "取消管理员" };
+            format!("[INFO] 群管理员变动 [群({})] [{}] {}", group_id, user_id, action)
+        }
+        NoticeEvent::GroupDecrease { sub_type, group_id, operator_id, user_id } => {
+            let action = match sub_type.as_str() {
+                "leave" => "主动退群".to_string(),
+                "kick" => format!("被 {} 踢出", operator_id),
+                "kick_me" => format!("机器人被 {} 踢出", operator_id),
+                other => other.to_string(),
+            };
+            format!("[INFO] 群成员减少 [群({})] [{}] {}", group_id, user_id, action)
+        }
+        NoticeEvent::GroupIncrease { sub_type, group_id, operator_id, user_id } => {
+            let action = match sub_type.as_str() {
+                "approve" => format!("由 {} 同意入群", operator_id),
+                "invite" => format!("由 {} 邀请入群", operator_id),
+                other => other.to_string(),
+            };
+            format!("[INFO] 群成员增加 [群({})] [{}] {}", group_id, user_id, action)
+        }
+        NoticeEvent::GroupBan { sub_type, group_id, operator_id, user_id, duration } => {
+            let target = if *user_id == 0 { "全员".to_string() } else { user_id.to_string() };
+            if sub_type == "lift_ban" {
+                format!("[INFO] 群禁言解除 [群({})] [{}] 操作者 {}", group_id, target, operator_id)
+            } else {
+                format!("[INFO] 群禁言 [群({})] [{}] {}秒 操作者 {}", group_id, target, duration, operator_id)
+            }
+        }
+        NoticeEvent::FriendAdd { user_id } => {
+            format!("[INFO] 新增好友 [{}]", user_id)
+        }
+        NoticeEvent::GroupRecall { group_id, user_id, operator_id, message_id } => {
+            format!("[INFO] 群消息撤回 [群({})] [{}] 消息 {} 操作者 {}", group_id, user_id, message_id, operator_id)
+        }
+        NoticeEvent::FriendRecall { user_id, message_id } => {
+            format!("[INFO] 好友消息撤回 [{}] 消息 {}", user_id, message_id)
+        }
+        NoticeEvent::Essence { sub_type, group_id, sender_id, operator_id, message_id } => {
+            let action = if sub_type == "add" { "设为精华" } else { "移出精华" };
+            format!("[INFO] 群精华消息 [群({})] [{}] 消息 {} 被 {} {}", group_id, sender_id, message_id, operator_id, action)
+        }
+        NoticeEvent::Notify(NotifyEvent::Poke { group_id, user_id, target_id }) => match group_id {
+            Some(gid) => format!("[INFO] 戳一戳 [群({})] {} -> {}", gid, user_id, target_id),
+            None => format!("[INFO] 戳一戳 [私聊] {} -> {}", user_id, target_id),
+        },
+        NoticeEvent::Notify(NotifyEvent::LuckyKing { group_id, user_id, target_id }) => {
+            format!("[INFO] 红包运气王 [群({})] {} 的红包运气王是 {}", group_id, user_id, target_id)
+        }
+        NoticeEvent::Notify(NotifyEvent::Honor { group_id, honor_type, user_id }) => {
+            let honor = match honor_type.as_str() {
+                "talkative" => "龙王",
+                "performer" => "群聊之火",
+                "emotion" => "快乐源泉",
+                other => other,
+            };
+            format!("[INFO] 群荣誉变更 [群({})] [{}] {}", group_id, user_id, honor)
+        }
+        NoticeEvent::Notify(NotifyEvent::Other { sub_type, .. }) => {
+            format!("[INFO] 提醒事件: {}", sub_type)
+        }
+        NoticeEvent::Other { notice_type, .. } => {
             format!("[INFO] 通知事件: {}", notice_type)
         }
-        OneBotEvent::Request { request_type, .. } => {
+    }
+}
+
+/// 格式化请求事件
+fn format_request_log(request: &RequestEvent) -> String {
+    match request {
+        RequestEvent::Friend { user_id, comment, .. } => {
+            format!("[INFO] 好友请求 [{}] {}", user_id, comment)
+        }
+        RequestEvent::Group { sub_type, group_id, user_id, comment, .. } => {
+            let action = if sub_type == "invite" { "邀请机器人入群" } else { "申请加群" };
+            format!("[INFO] 群请求 [群({})] [{}] {} {}", group_id, user_id, action, comment)
+        }
+        RequestEvent::Other { request_type, .. } => {
             format!("[INFO] 请求事件: {}", request_type)
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn parse(value: Value) -> OneBotEvent {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn notice(value: Value) -> NoticeEvent {
+        match parse(value) {
+            OneBotEvent::Notice { self_id, notice, .. } => {
+                assert_eq!(self_id, 10001);
+                notice
+            }
+            other => panic!("不是通知事件: {:?}", other),
+        }
+    }
+
+    fn request(value: Value) -> RequestEvent {
+        match parse(value) {
+            OneBotEvent::Request { request, .. } => request,
+            other => panic!("不是请求事件: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn group_increase_notice_is_typed() {
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "group_increase",
+            "sub_type": "approve",
+            "group_id": 20002,
+            "operator_id": 40004,
+            "user_id": 30003,
+        }));
+        let NoticeEvent::GroupIncrease { sub_type, group_id, operator_id, user_id } = &event else {
+            panic!("未解析为 GroupIncrease: {:?}", event);
+        };
+        assert_eq!((sub_type.as_str(), *group_id, *operator_id, *user_id), ("approve", 20002, 40004, 30003));
+        assert_eq!(event.notice_type(), "group_increase");
+        assert_eq!(event.group_id(), Some(20002));
+    }
+
+    #[test]
+    fn poke_is_typed_as_notify() {
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "notify",
+            "sub_type": "poke",
+            "group_id": 20002,
+            "user_id": 30003,
+            "target_id": 10001,
+        }));
+        let NoticeEvent::Notify(NotifyEvent::Poke { group_id, user_id, target_id }) = event else {
+            panic!("未解析为戳一戳: {:?}", event);
+        };
+        assert_eq!((group_id, user_id, target_id), (Some(20002), 30003, 10001));
+
+        // 私聊戳一戳没有 group_id
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "notify",
+            "sub_type": "poke",
+            "user_id": 30003,
+            "target_id": 10001,
+        }));
+        assert!(matches!(event, NoticeEvent::Notify(NotifyEvent::Poke { group_id: None, .. })));
+    }
+
+    #[test]
+    fn friend_and_group_requests_are_typed() {
+        let event = request(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "request",
+            "request_type": "friend",
+            "user_id": 30003,
+            "comment": "你好",
+            "flag": "f-1",
+        }));
+        let RequestEvent::Friend { user_id, comment, flag } = &event else {
+            panic!("未解析为好友请求: {:?}", event);
+        };
+        assert_eq!((*user_id, comment.as_str(), flag.as_str()), (30003, "你好", "f-1"));
+        assert_eq!(event.group_id(), None);
+
+        // 缺少 comment 时按空字符串处理
+        let event = request(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "request",
+            "request_type": "group",
+            "sub_type": "invite",
+            "group_id": 20002,
+            "user_id": 30003,
+            "flag": "g-1",
+        }));
+        let RequestEvent::Group { sub_type, group_id, user_id, comment, flag } = &event else {
+            panic!("未解析为群请求: {:?}", event);
+        };
+        assert_eq!(
+            (sub_type.as_str(), *group_id, *user_id, comment.as_str(), flag.as_str()),
+            ("invite", 20002, 30003, "", "g-1"),
+        );
+        assert_eq!(event.request_type(), "group");
+    }
+
+    #[test]
+    fn unknown_notice_type_falls_back_to_other() {
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "group_card",
+            "group_id": 20002,
+            "user_id": 30003,
+            "card_new": "新名片",
+            "card_old": "旧名片",
+        }));
+        let NoticeEvent::Other { notice_type, sub_type, extra } = &event else {
+            panic!("未落入 Other: {:?}", event);
+        };
+        assert_eq!(notice_type, "group_card");
+        assert_eq!(*sub_type, None);
+        assert_eq!(extra["card_new"], "新名片");
+        assert_eq!(extra["card_old"], "旧名片");
+        assert_eq!(event.user_id(), Some(30003));
+        assert_eq!(event.group_id(), Some(20002));
+
+        // 已知类型但字段不完整时同样落入 Other，不丢弃事件
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "group_ban",
+            "sub_type": "ban",
+            "group_id": 20002,
+        }));
+        assert!(matches!(&event, NoticeEvent::Other { notice_type, .. } if notice_type == "group_ban"));
+
+        // 未知提醒保留 sub_type 与其余字段
+        let event = notice(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "notice",
+            "notice_type": "notify",
+            "sub_type": "title",
+            "group_id": 20002,
+            "user_id": 30003,
+            "title": "头衔",
+        }));
+        let NoticeEvent::Notify(NotifyEvent::Other { sub_type, extra }) = &event else {
+            panic!("未落入 NotifyEvent::Other: {:?}", event);
+        };
+        assert_eq!(sub_type, "title");
+        assert_eq!(extra["title"], "头衔");
+    }
+
+    #[test]
+    fn message_sent_frame_is_parsed_as_message_sent() {
+        let event = parse(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": "message_sent",
+            "message_type": "private",
+            "sub_type": "friend",
+            "message_id": 7,
+            "user_id": 10001,
+            "target_id": 30003,
+            "message": [{ "type": "text", "data": { "text": "来自其他设备" } }],
+            "raw_message": "来自其他设备",
+            "font": 0,
+            "sender": { "user_id": 10001, "nickname": "bot" },
+        }));
+        let OneBotEvent::MessageSent { self_id, message_type, user_id, target_id, raw_message, .. } = &event else {
+            panic!("未解析为 MessageSent: {:?}", event);
+        };
+        assert_eq!((*self_id, message_type.as_str(), *user_id), (10001, "private", 10001));
+        assert_eq!(*target_id, Some(30003));
+        assert_eq!(raw_message, "来自其他设备");
+        assert_eq!(serde_json::to_value(&event).unwrap()["post_type"], "message_sent");
+    }
+}
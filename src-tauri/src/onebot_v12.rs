@@ -0,0 +1,746 @@
+//! OneBot v12 协议模型与 v11 适配
+//!
+//! 内部统一使用 v11 的 [`OneBotEvent`]：v12 连接收到的事件在入口处转换为 v11 形式，
+//! 发往 v12 连接的动作在出口处转换为 v12 形式，动作响应再转换回 v11 字段。
+
+use crate::onebot::{OneBotApiRequest, OneBotApiResponse, OneBotEvent, OneBotVersion};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// v12 机器人自身标识
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotSelf {
+    pub platform: String,
+    pub user_id: String,
+}
+
+/// v12 事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneBotV12Event {
+    pub id: String,
+    pub time: f64,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub detail_type: String,
+    #[serde(default)]
+    pub sub_type: String,
+    #[serde(rename = "self", skip_serializing_if = "Option::is_none")]
+    pub bot_self: Option<BotSelf>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// v12 动作请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneBotV12ActionRequest {
+    pub action: String,
+    pub params: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<String>,
+    #[serde(rename = "self", skip_serializing_if = "Option::is_none")]
+    pub bot_self: Option<BotSelf>,
+}
+
+/// v12 动作响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct OneBotV12ActionResponse {
+    pub status: String,
+    pub retcode: i64,
+    #[serde(default)]
+    pub data: Value,
+    #[serde(default)]
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<String>,
+}
+
+/// 每个服务器最多记录的字符串 ID 数量
+pub const DEFAULT_STRING_ID_CAPACITY: usize = 10_000;
+
+/// 非数字字符串 ID 与映射后的 v11 整数 ID 的对应关系，用于动作参数还原
+///
+/// 每个服务器持有一份（分离式连接的 API 与 Event 连接需要共用），
+/// 超过容量时淘汰最久未使用的 ID，长时间运行也不会无限增长。
+pub struct StringIdMap {
+    capacity: usize,
+    inner: Mutex<StringIdMapInner>,
+}
+
+#[derive(Default)]
+struct StringIdMapInner {
+    /// v11 ID -> (字符串 ID, 最近使用序号)
+    ids: HashMap<i64, (String, u64)>,
+    /// 最近使用序号 -> v11 ID，按序号淘汰
+    order: BTreeMap<u64, i64>,
+    tick: u64,
+}
+
+impl StringIdMapInner {
+    /// 标记 ID 为最近使用
+    fn touch(&mut self, number: i64) -> Option<&String> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (_, used) = self.ids.get_mut(&number)?;
+        self.order.remove(used);
+        *used = tick;
+        self.order.insert(tick, number);
+        self.ids.get(&number).map(|(id, _)| id)
+    }
+}
+
+impl StringIdMap {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(StringIdMapInner::default()),
+        }
+    }
+
+    /// v12 字符串 ID 转为 v11 整数 ID，非数字 ID 取稳定哈希并记录以便还原
+    pub fn to_v11(&self, id: &str) -> i64 {
+        if let Ok(number) = id.parse::<i64>() {
+            return number;
+        }
+
+        // FNV-1a，保证同一字符串每次得到同一 ID
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in id.as_bytes() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        let number = (hash >> 1) as i64;
+
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.touch(number).is_none() {
+            inner.tick += 1;
+            let tick = inner.tick;
+            inner.ids.insert(number, (id.to_string(), tick));
+            inner.order.insert(tick, number);
+
+            while inner.ids.len() > self.capacity {
+                let Some((_, oldest)) = inner.order.pop_first() else {
+                    break;
+                };
+                inner.ids.remove(&oldest);
+            }
+        }
+        number
+    }
+
+    /// v11 整数 ID 还原为 v12 字符串 ID
+    pub fn to_v12(&self, id: i64) -> String {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.touch(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// 当前记录的字符串 ID 数量
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).ids.len()
+    }
+}
+
+impl Default for StringIdMap {
+    fn default() -> Self {
+        Self::new(DEFAULT_STRING_ID_CAPACITY)
+    }
+}
+
+/// 判断 JSON 是否为 v12 事件
+pub fn is_v12_event(value: &Value) -> bool {
+    value.get("post_type").is_none()
+        && value.get("type").is_some_and(Value::is_string)
+        && value.get("detail_type").is_some_and(Value::is_string)
+}
+
+/// 解析任意版本的事件，统一返回 v11 事件及其原始协议版本
+///
+/// `fallback_self_id` 用于不携带 `self` 字段的 v12 元事件，`ids` 记录事件中出现的字符串 ID。
+pub fn parse_event(text: &str, fallback_self_id: Option<i64>, ids: &StringIdMap) -> Result<(OneBotEvent, OneBotVersion), String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    if is_v12_event(&value) {
+        let event: OneBotV12Event = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        let event = event_to_v11(event, fallback_self_id, ids)?;
+        Ok((event, OneBotVersion::V12))
+    } else {
+        let event = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        Ok((event, OneBotVersion::V11))
+    }
+}
+
+/// 取出字段中的 ID 并转为 v11 形式
+fn take_id(fields: &mut Map<String, Value>, key: &str, ids: &StringIdMap) -> Option<i64> {
+    match fields.remove(key)? {
+        Value::String(id) => Some(ids.to_v11(&id)),
+        Value::Number(number) => number.as_i64(),
+        _ => None,
+    }
+}
+
+/// 把剩余字段中的 `*_id` 字符串转为 v11 整数 ID
+fn convert_id_fields(fields: Map<String, Value>, ids: &StringIdMap) -> Map<String, Value> {
+    fields.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(id) if key.ends_with("_id") => json!(ids.to_v11(&id)),
+                other => other,
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// v12 事件转换为 v11 事件
+pub fn event_to_v11(event: OneBotV12Event, fallback_self_id: Option<i64>, ids: &StringIdMap) -> Result<OneBotEvent, String> {
+    let time = event.time as i64;
+    let self_id = event.bot_self.as_ref()
+        .map(|bot| ids.to_v11(&bot.user_id))
+        .or(fallback_self_id)
+        .unwrap_or(0);
+    let mut fields = event.extra;
+
+    let value = match event.event_type.as_str() {
+        "message" => {
+            let message_type = match event.detail_type.as_str() {
+                "private" | "group" => event.detail_type.clone(),
+                other => return Err(format!("暂不支持的 v12 消息类型: {}", other)),
+            };
+            let sub_type = if !event.sub_type.is_empty() {
+                event.sub_type.clone()
+            } else if message_type == "private" {
+                "friend".to_string()
+            } else {
+                "normal".to_string()
+            };
+
+            let user_id = take_id(&mut fields, "user_id", ids).unwrap_or(0);
+            let group_id = take_id(&mut fields, "group_id", ids);
+            let message_id = take_id(&mut fields, "message_id", ids).unwrap_or_else(|| ids.to_v11(&event.id));
+            let message = segments_to_v11(fields.remove("message").unwrap_or(Value::Array(Vec::new())));
+            let raw_message = match fields.remove("alt_message") {
+                Some(Value::String(alt)) if !alt.is_empty() => alt,
+                _ => segments_to_cq(&message),
+            };
+            let nickname = fields.get("user_name").and_then(Value::as_str).unwrap_or_default().to_string();
+
+            let mut value = json!({
+                "post_type": "message",
+                "time": time,
+                "self_id": self_id,
+                "message_type": message_type,
+                "sub_type": sub_type,
+                "message_id": message_id,
+                "user_id": user_id,
+                "message": message,
+                "raw_message": raw_message,
+                "font": 0,
+                "sender": { "user_id": user_id, "nickname": nickname },
+            });
+            if let Some(group_id) = group_id {
+                value["group_id"] = json!(group_id);
+            }
+            value
+        }
+        "notice" => {
+            let (notice_type, sub_type) = match (event.detail_type.as_str(), event.sub_type.as_str()) {
+                ("friend_increase", sub) => ("friend_add", sub.to_string()),
+                ("private_message_delete", sub) => ("friend_recall", sub.to_string()),
+                ("group_member_increase", "join") => ("group_increase", "approve".to_string()),
+                ("group_member_increase", sub) => ("group_increase", sub.to_string()),
+                ("group_member_decrease", sub) => ("group_decrease", sub.to_string()),
+                ("group_message_delete", sub) => ("group_recall", sub.to_string()),
+                (other, sub) => (other, sub.to_string()),
+            };
+            let user_id = take_id(&mut fields, "user_id", ids).unwrap_or(0);
+            let group_id = take_id(&mut fields, "group_id", ids);
+
+            let mut value = Value::Object(convert_id_fields(fields, ids));
+            value["post_type"] = json!("notice");
+            value["time"] = json!(time);
+            value["self_id"] = json!(self_id);
+            value["notice_type"] = json!(notice_type);
+            value["sub_type"] = json!(sub_type);
+            value["user_id"] = json!(user_id);
+            if let Some(group_id) = group_id {
+                value["group_id"] = json!(group_id);
+            }
+            value
+        }
+        "request" => {
+            let user_id = take_id(&mut fields, "user_id", ids).unwrap_or(0);
+            let group_id = take_id(&mut fields, "group_id", ids);
+            let comment = fields.get("comment").and_then(Value::as_str).unwrap_or_default().to_string();
+            let flag = fields.get("request_id")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| event.id.clone());
+
+            let mut value = json!({
+                "post_type": "request",
+                "time": time,
+                "self_id": self_id,
+                "request_type": event.detail_type.trim_end_matches("_request"),
+                "sub_type": event.sub_type,
+                "user_id": user_id,
+                "comment": comment,
+                "flag": flag,
+            });
+            if let Some(group_id) = group_id {
+                value["group_id"] = json!(group_id);
+            }
+            value
+        }
+        "meta" => {
+            let (meta_event_type, sub_type) = match event.detail_type.as_str() {
+                "connect" => ("lifecycle".to_string(), Some("connect".to_string())),
+                other => (other.to_string(), (!event.sub_type.is_empty()).then(|| event.sub_type.clone())),
+            };
+
+            // v12 的 status 结构与 v11 心跳状态不同，单独保存
+            if let Some(status) = fields.remove("status") {
+                fields.insert("status_detail".to_string(), status);
+            }
+
+            let mut value = Value::Object(fields);
+            value["post_type"] = json!("meta_event");
+            value["time"] = json!(time);
+            value["self_id"] = json!(self_id);
+            value["meta_event_type"] = json!(meta_event_type);
+            if let Some(sub_type) = sub_type {
+                value["sub_type"] = json!(sub_type);
+            }
+            value
+        }
+        other => return Err(format!("未知的 v12 事件类型: {}", other)),
+    };
+
+    serde_json::from_value(value).map_err(|e| format!("转换 v12 事件失败: {}", e))
+}
+
+/// v12 消息段转换为 v11 消息段
+fn segments_to_v11(message: Value) -> Value {
+    let Value::Array(segments) = message else {
+        return message;
+    };
+
+    Value::Array(segments.into_iter().map(|segment| {
+        let seg_type = segment.get("type").and_then(Value::as_str).unwrap_or_default();
+        let data = segment.get("data").cloned().unwrap_or_else(|| json!({}));
+        match seg_type {
+            "mention" => json!({ "type": "at", "data": { "qq": data.get("user_id").cloned().unwrap_or_default() } }),
+            "mention_all" => json!({ "type": "at", "data": { "qq": "all" } }),
+            "image" | "voice" | "audio" | "video" | "file" => {
+                let v11_type = match seg_type {
+                    "voice" | "audio" => "record",
+                    other => other,
+                };
+                json!({ "type": v11_type, "data": { "file": data.get("file_id").cloned().unwrap_or_default() } })
+            }
+            "reply" => json!({ "type": "reply", "data": { "id": data.get("message_id").cloned().unwrap_or_default() } }),
+            _ => segment,
+        }
+    }).collect())
+}
+
+/// v11 消息（字符串或消息段）转换为 v12 消息段
+fn message_to_v12(message: Value) -> Value {
+    let segments = match message {
+        Value::String(text) => return json!([{ "type": "text", "data": { "text": text } }]),
+        Value::Array(segments) => segments,
+        other => return other,
+    };
+
+    Value::Array(segments.into_iter().map(|segment| {
+        let seg_type = segment.get("type").and_then(Value::as_str).unwrap_or_default();
+        let data = segment.get("data").cloned().unwrap_or_else(|| json!({}));
+        let value_string = |key: &str| match data.get(key) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        match seg_type {
+            "at" if value_string("qq") == "all" => json!({ "type": "mention_all", "data": {} }),
+            "at" => json!({ "type": "mention", "data": { "user_id": value_string("qq") } }),
+            "image" | "video" | "file" => json!({ "type": seg_type, "data": { "file_id": value_string("file") } }),
+            "record" => json!({ "type": "voice", "data": { "file_id": value_string("file") } }),
+            "reply" => json!({ "type": "reply", "data": { "message_id": value_string("id") } }),
+            _ => segment,
+        }
+    }).collect())
+}
+
+/// v11 消息段拼接为 CQ 码字符串，作为 `raw_message`
+fn segments_to_cq(message: &Value) -> String {
+    let Some(segments) = message.as_array() else {
+        return message.as_str().unwrap_or_default().to_string();
+    };
+
+    let escape = |text: &str| text.replace('&', "&amp;").replace('[', "&#91;").replace(']', "&#93;");
+    segments.iter().map(|segment| {
+        let seg_type = segment.get("type").and_then(Value::as_str).unwrap_or_default();
+        let data = segment.get("data").and_then(Value::as_object);
+        if seg_type == "text" {
+            return escape(data.and_then(|d| d.get("text")).and_then(Value::as_str).unwrap_or_default());
+        }
+
+        let params: String = data.into_iter().flatten()
+            .map(|(key, value)| {
+                let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                format!(",{}={}", key, escape(&value).replace(',', "&#44;"))
+            })
+            .collect();
+        format!("[CQ:{}{}]", seg_type, params)
+    }).collect()
+}
+
+/// v11 动作请求转换为 v12 动作请求
+pub fn action_to_v12(request: &OneBotApiRequest, self_id: i64, ids: &StringIdMap) -> OneBotV12ActionRequest {
+    let mut params: Map<String, Value> = request.params.iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    let action = match request.action.as_str() {
+        "send_private_msg" => {
+            params.insert("detail_type".to_string(), json!("private"));
+            "send_message"
+        }
+        "send_group_msg" => {
+            params.insert("detail_type".to_string(), json!("group"));
+            "send_message"
+        }
+        "send_msg" => {
+            if let Some(message_type) = params.remove("message_type") {
+                params.insert("detail_type".to_string(), message_type);
+            }
+            "send_message"
+        }
+        "delete_msg" => "delete_message",
+        "get_msg" => "get_message",
+        "get_login_info" => "get_self_info",
+        "get_stranger_info" => "get_user_info",
+        "set_group_leave" => "leave_group",
+        "get_version_info" => "get_version",
+        other => other,
+    }.to_string();
+
+    // v11 独有的参数 v12 不识别，直接丢弃
+    params.remove("auto_escape");
+    params.remove("no_cache");
+
+    if let Some(message) = params.remove("message") {
+        params.insert("message".to_string(), message_to_v12(message));
+    }
+
+    let params = params.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Number(number) if key.ends_with("_id") => match number.as_i64() {
+                    Some(id) => json!(ids.to_v12(id)),
+                    None => Value::Number(number),
+                },
+                other => other,
+            };
+            (key, value)
+        })
+        .collect();
+
+    OneBotV12ActionRequest {
+        action,
+        params,
+        echo: request.echo.clone(),
+        bot_self: Some(BotSelf {
+            platform: "qq".to_string(),
+            user_id: ids.to_v12(self_id),
+        }),
+    }
+}
+
+/// 把 v12 动作响应的数据字段转换为 v11 字段名与 ID 形式
+pub fn normalize_response(mut response: OneBotApiResponse, ids: &StringIdMap) -> OneBotApiResponse {
+    response.data = response.data.map(|data| normalize_data(data, ids));
+    response
+}
+
+/// 递归转换响应数据
+fn normalize_data(data: Value, ids: &StringIdMap) -> Value {
+    match data {
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| normalize_data(item, ids)).collect()),
+        Value::Object(fields) => {
+            let mut converted = Map::new();
+            for (key, value) in fields {
+                let key = match key.as_str() {
+                    "user_name" => "nickname".to_string(),
+                    "user_displayname" => "card".to_string(),
+                    "user_remark" => "remark".to_string(),
+                    "impl" => "app_name".to_string(),
+                    "version" => "app_version".to_string(),
+                    _ => key,
+                };
+                let value = match value {
+                    Value::String(id) if key.ends_with("_id") => json!(ids.to_v11(&id)),
+                    other => normalize_data(other, ids),
+                };
+                converted.insert(key, value);
+            }
+            Value::Object(converted)
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_json(event: &OneBotEvent) -> Value {
+        serde_json::to_value(event).unwrap()
+    }
+
+    #[test]
+    fn group_message_is_normalized_to_v11() {
+        let ids = StringIdMap::default();
+        let text = json!({
+            "id": "evt-1",
+            "time": 1700000000.5,
+            "type": "message",
+            "detail_type": "group",
+            "sub_type": "",
+            "self": { "platform": "qq", "user_id": "10001" },
+            "message_id": "m-1",
+            "group_id": "20002",
+            "user_id": "30003",
+            "user_name": "小明",
+            "message": [
+                { "type": "text", "data": { "text": "a&b" } },
+                { "type": "mention", "data": { "user_id": "40004" } },
+                { "type": "mention_all", "data": {} },
+                { "type": "voice", "data": { "file_id": "f1" } },
+                { "type": "reply", "data": { "message_id": "m-0" } },
+            ],
+            "alt_message": "",
+        }).to_string();
+
+        let (event, version) = parse_event(&text, None, &ids).unwrap();
+        assert!(matches!(version, OneBotVersion::V12));
+        let value = to_json(&event);
+        assert_eq!(value["post_type"], "message");
+        assert_eq!(value["time"], 1700000000);
+        assert_eq!(value["self_id"], 10001);
+        assert_eq!(value["message_type"], "group");
+        assert_eq!(value["sub_type"], "normal");
+        assert_eq!(value["group_id"], 20002);
+        assert_eq!(value["user_id"], 30003);
+        assert_eq!(value["sender"]["nickname"], "小明");
+        assert_eq!(value["message_id"], ids.to_v11("m-1"));
+        assert_eq!(
+            value["raw_message"],
+            "a&amp;b[CQ:at,qq=40004][CQ:at,qq=all][CQ:record,file=f1][CQ:reply,id=m-0]"
+        );
+    }
+
+    #[test]
+    fn private_message_defaults_sub_type_to_friend() {
+        let ids = StringIdMap::default();
+        let text = json!({
+            "id": "evt-2",
+            "time": 1700000000,
+            "type": "message",
+            "detail_type": "private",
+            "self": { "platform": "qq", "user_id": "10001" },
+            "message_id": "1",
+            "user_id": "30003",
+            "message": [{ "type": "text", "data": { "text": "hi" } }],
+            "alt_message": "hi",
+        }).to_string();
+
+        let value = to_json(&parse_event(&text, None, &ids).unwrap().0);
+        assert_eq!(value["message_type"], "private");
+        assert_eq!(value["sub_type"], "friend");
+        assert_eq!(value["raw_message"], "hi");
+        assert!(value.get("group_id").is_none());
+    }
+
+    #[test]
+    fn notice_types_are_mapped() {
+        let ids = StringIdMap::default();
+        let text = json!({
+            "id": "evt-3",
+            "time": 1700000000,
+            "type": "notice",
+            "detail_type": "group_member_increase",
+            "sub_type": "join",
+            "self": { "platform": "qq", "user_id": "10001" },
+            "group_id": "20002",
+            "user_id": "30003",
+            "operator_id": "30003",
+        }).to_string();
+
+        let value = to_json(&parse_event(&text, None, &ids).unwrap().0);
+        assert_eq!(value["post_type"], "notice");
+        assert_eq!(value["notice_type"], "group_increase");
+        assert_eq!(value["sub_type"], "approve");
+        assert_eq!(value["group_id"], 20002);
+        assert_eq!(value["operator_id"], 30003);
+    }
+
+    #[test]
+    fn self_object_takes_precedence_over_fallback() {
+        let ids = StringIdMap::default();
+        let with_self = json!({
+            "id": "evt-4",
+            "time": 1700000000,
+            "type": "meta",
+            "detail_type": "heartbeat",
+            "self": { "platform": "qq", "user_id": "10001" },
+            "interval": 5000,
+        }).to_string();
+        let without_self = json!({
+            "id": "evt-5",
+            "time": 1700000000,
+            "type": "meta",
+            "detail_type": "connect",
+            "version": { "impl": "walle", "version": "1.0", "onebot_version": "12" },
+        }).to_string();
+
+        assert_eq!(parse_event(&with_self, Some(99), &ids).unwrap().0.self_id(), 10001);
+        let event = parse_event(&without_self, Some(99), &ids).unwrap().0;
+        assert_eq!(event.self_id(), 99);
+        let value = to_json(&event);
+        assert_eq!(value["meta_event_type"], "lifecycle");
+        assert_eq!(value["sub_type"], "connect");
+        assert_eq!(parse_event(&without_self, None, &ids).unwrap().0.self_id(), 0);
+    }
+
+    #[test]
+    fn v11_events_pass_through() {
+        let ids = StringIdMap::default();
+        let text = json!({
+            "post_type": "meta_event",
+            "time": 1700000000,
+            "self_id": 10001,
+            "meta_event_type": "heartbeat",
+            "interval": 5000,
+        }).to_string();
+
+        let (event, version) = parse_event(&text, Some(99), &ids).unwrap();
+        assert!(matches!(version, OneBotVersion::V11));
+        assert_eq!(event.self_id(), 10001);
+        assert_eq!(ids.len(), 0);
+    }
+
+    #[test]
+    fn string_ids_round_trip() {
+        let ids = StringIdMap::default();
+        assert_eq!(ids.to_v11("12345"), 12345);
+        assert_eq!(ids.to_v12(12345), "12345");
+        assert_eq!(ids.len(), 0);
+
+        let number = ids.to_v11("u_abc");
+        assert!(number >= 0);
+        assert_eq!(ids.to_v11("u_abc"), number);
+        assert_eq!(ids.to_v12(number), "u_abc");
+        assert_eq!(ids.len(), 1);
+    }
+
+    #[test]
+    fn string_ids_evict_least_recently_used() {
+        let ids = StringIdMap::new(2);
+        let a = ids.to_v11("a");
+        let b = ids.to_v11("b");
+        // 使用 a 之后再加入 c，淘汰的应是 b
+        assert_eq!(ids.to_v12(a), "a");
+        let c = ids.to_v11("c");
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids.to_v12(a), "a");
+        assert_eq!(ids.to_v12(c), "c");
+        assert_eq!(ids.to_v12(b), b.to_string());
+    }
+
+    #[test]
+    fn send_action_is_converted_to_v12() {
+        let ids = StringIdMap::default();
+        let group = ids.to_v11("g_1");
+        let bot = ids.to_v11("bot_1");
+        let request = OneBotApiRequest {
+            action: "send_group_msg".to_string(),
+            params: HashMap::from([
+                ("group_id".to_string(), json!(group)),
+                ("message".to_string(), json!([
+                    { "type": "at", "data": { "qq": "all" } },
+                    { "type": "at", "data": { "qq": 123 } },
+                    { "type": "record", "data": { "file": "f1" } },
+                ])),
+                ("auto_escape".to_string(), json!(false)),
+            ]),
+            echo: Some("e1".to_string()),
+        };
+
+        let action = serde_json::to_value(action_to_v12(&request, bot, &ids)).unwrap();
+        assert_eq!(action, json!({
+            "action": "send_message",
+            "params": {
+                "detail_type": "group",
+                "group_id": "g_1",
+                "message": [
+                    { "type": "mention_all", "data": {} },
+                    { "type": "mention", "data": { "user_id": "123" } },
+                    { "type": "voice", "data": { "file_id": "f1" } },
+                ],
+            },
+            "echo": "e1",
+            "self": { "platform": "qq", "user_id": "bot_1" },
+        }));
+    }
+
+    #[test]
+    fn string_message_is_wrapped_as_text_segment() {
+        let ids = StringIdMap::default();
+        let request = OneBotApiRequest {
+            action: "send_msg".to_string(),
+            params: HashMap::from([
+                ("message_type".to_string(), json!("private")),
+                ("user_id".to_string(), json!(30003)),
+                ("message".to_string(), json!("hello")),
+            ]),
+            echo: None,
+        };
+
+        let action = action_to_v12(&request, 10001, &ids);
+        assert_eq!(action.action, "send_message");
+        assert_eq!(action.params["detail_type"], "private");
+        assert_eq!(action.params["user_id"], "30003");
+        assert_eq!(action.params["message"], json!([{ "type": "text", "data": { "text": "hello" } }]));
+        assert_eq!(action.bot_self.unwrap().user_id, "10001");
+    }
+
+    #[test]
+    fn response_fields_are_normalized() {
+        let ids = StringIdMap::default();
+        let response = OneBotApiResponse {
+            status: "ok".to_string(),
+            retcode: 0,
+            data: Some(json!([
+                { "user_id": "30003", "user_name": "小明", "user_displayname": "群名片", "user_remark": "备注" },
+                { "user_id": "u_abc", "impl": "walle", "version": "1.0" },
+            ])),
+            echo: Some("e1".to_string()),
+            message: None,
+            wording: None,
+        };
+
+        let response = normalize_response(response, &ids);
+        let data = response.data.unwrap();
+        assert_eq!(data[0], json!({ "user_id": 30003, "nickname": "小明", "card": "群名片", "remark": "备注" }));
+        assert_eq!(data[1]["user_id"], ids.to_v11("u_abc"));
+        assert_eq!(data[1]["app_name"], "walle");
+        assert_eq!(data[1]["app_version"], "1.0");
+        assert_eq!(response.echo.as_deref(), Some("e1"));
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 发件箱中等待发送的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    pub id: String,
+    pub self_id: i64,
+    pub action: String,
+    pub params: HashMap<String, serde_json::Value>,
+    pub created_at: i64, // 加入发件箱的时间（秒）
+    pub expires_at: i64, // 过期时间（秒），过期后不再发送
+}
+
+impl OutboxMessage {
+    /// 检查消息是否已过期
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// 持久化的发件箱
+///
+/// 机器人离线时发送的消息按加入顺序保存在磁盘上，机器人重新上线后按顺序重放。
+/// 修改发件箱后返回 [`OutboxSnapshot`]，调用方释放发件箱的锁后再写入磁盘。
+pub struct Outbox {
+    path: PathBuf,
+    messages: Vec<OutboxMessage>,
+    /// 正在重放发件箱的机器人，避免同一机器人并发重放导致乱序
+    replaying: HashSet<i64>,
+    /// 每次修改递增，用于丢弃过时的快照
+    generation: u64,
+    /// 已写入磁盘的快照序号
+    written: Arc<Mutex<u64>>,
+}
+
+/// 待写入磁盘的发件箱内容
+pub struct OutboxSnapshot {
+    path: PathBuf,
+    messages: Vec<OutboxMessage>,
+    generation: u64,
+    written: Arc<Mutex<u64>>,
+}
+
+impl OutboxSnapshot {
+    /// 在阻塞线程中写入磁盘，比已写入的快照更旧时跳过
+    pub async fn save(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tokio::task::spawn_blocking(move || self.write())
+            .await
+            .map_err(|e| format!("写入发件箱失败: {}", e))?
+    }
+
+    fn write(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut written = self.written.lock().unwrap_or_else(|e| e.into_inner());
+        if *written >= self.generation {
+            return Ok(());
+        }
+
+        let content = serde_json::to_string_pretty(&self.messages)
+            .map_err(|e| format!("序列化发件箱失败: {}", e))?;
+        write_atomic(&self.path, content.as_bytes())
+            .map_err(|e| format!("写入发件箱失败: {}", e))?;
+        *written = self.generation;
+        Ok(())
+    }
+}
+
+/// 先写入临时文件并同步到磁盘，再重命名覆盖目标文件，写入中途崩溃不会损坏原文件
+fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
+
+    // 同步目录项，保证重命名本身也已落盘
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+impl Outbox {
+    /// 从文件加载发件箱，文件不存在时创建空发件箱
+    ///
+    /// 文件无法读取或解析时将其重命名备份，以空发件箱继续运行，同时返回错误说明供调用方记录。
+    pub fn load(path: PathBuf) -> (Self, Option<String>) {
+        let (messages, error) = match Self::read(&path) {
+            Ok(messages) => (messages, None),
+            Err(e) => {
+                let backup = path.with_extension(format!("json.{}.bak", chrono::Utc::now().timestamp()));
+                let error = match fs::rename(&path, &backup) {
+                    Ok(()) => format!("{}，原文件已备份为 {}", e, backup.display()),
+                    Err(rename_error) => format!("{}，备份原文件失败: {}", e, rename_error),
+                };
+                (Vec::new(), Some(error))
+            }
+        };
+
+        let outbox = Self {
+            path,
+            messages,
+            replaying: HashSet::new(),
+            generation: 0,
+            written: Arc::new(Mutex::new(0)),
+        };
+        (outbox, error)
+    }
+
+    fn read(path: &Path) -> Result<Vec<OutboxMessage>, String> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("读取发件箱失败: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("解析发件箱失败: {}", e))
+    }
+
+    /// 当前内容的快照
+    fn snapshot(&mut self) -> OutboxSnapshot {
+        self.generation += 1;
+        OutboxSnapshot {
+            path: self.path.clone(),
+            messages: self.messages.clone(),
+            generation: self.generation,
+            written: Arc::clone(&self.written),
+        }
+    }
+
+    /// 加入一条消息
+    pub fn push(&mut self, message: OutboxMessage) -> OutboxSnapshot {
+        self.messages.push(message);
+        self.snapshot()
+    }
+
+    /// 获取所有未过期的消息
+    pub fn list(&self, now: i64) -> Vec<OutboxMessage> {
+        self.messages.iter()
+            .filter(|message| !message.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// 取消一条消息，消息不存在时返回 `None`
+    pub fn remove(&mut self, id: &str) -> Option<OutboxSnapshot> {
+        let before = self.messages.len();
+        self.messages.retain(|message| message.id != id);
+        (self.messages.len() != before).then(|| self.snapshot())
+    }
+
+    /// 移除已过期的消息，返回被移除的消息，有消息被移除时同时返回快照
+    pub fn prune_expired(&mut self, now: i64) -> (Vec<OutboxMessage>, Option<OutboxSnapshot>) {
+        let (expired, remaining): (Vec<_>, Vec<_>) = self.messages.drain(..)
+            .partition(|message| message.is_expired(now));
+        self.messages = remaining;
+        let snapshot = (!expired.is_empty()).then(|| self.snapshot());
+        (expired, snapshot)
+    }
+
+    /// 获取指定机器人最早加入的消息
+    pub fn front(&self, self_id: i64) -> Option<OutboxMessage> {
+        self.messages.iter()
+            .find(|message| message.self_id == self_id)
+            .cloned()
+    }
+
+    /// 检查指定机器人是否还有未发送的消息
+    pub fn has_pending(&self, self_id: i64) -> bool {
+        self.messages.iter().any(|message| message.self_id == self_id)
+    }
+
+    /// 标记机器人开始重放，已在重放时返回 false
+    pub fn begin_replay(&mut self, self_id: i64) -> bool {
+        self.replaying.insert(self_id)
+    }
+
+    /// 标记机器人重放结束
+    pub fn end_replay(&mut self, self_id: i64) {
+        self.replaying.remove(&self_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, expires_at: i64) -> OutboxMessage {
+        OutboxMessage {
+            id: id.to_string(),
+            self_id: 10001,
+            action: "send_private_msg".to_string(),
+            params: HashMap::from([("user_id".to_string(), serde_json::json!(30003))]),
+            created_at: 0,
+            expires_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_messages_survive_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.json");
+
+        let (mut outbox, error) = Outbox::load(path.clone());
+        assert!(error.is_none());
+        outbox.push(message("a", i64::MAX)).save().await.unwrap();
+        outbox.push(message("b", i64::MAX)).save().await.unwrap();
+        outbox.remove("a").unwrap().save().await.unwrap();
+        assert!(outbox.remove("a").is_none());
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let (reloaded, error) = Outbox::load(path);
+        assert!(error.is_none());
+        let ids: Vec<String> = reloaded.list(0).into_iter().map(|message| message.id).collect();
+        assert_eq!(ids, vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn stale_snapshot_does_not_overwrite_newer_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.json");
+
+        let (mut outbox, _) = Outbox::load(path.clone());
+        let older = outbox.push(message("a", i64::MAX));
+        let newer = outbox.push(message("b", i64::MAX));
+        newer.save().await.unwrap();
+        older.save().await.unwrap();
+
+        assert_eq!(Outbox::load(path).0.list(0).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn expired_messages_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut outbox, _) = Outbox::load(dir.path().join("outbox.json"));
+        let _ = outbox.push(message("old", 10));
+        let _ = outbox.push(message("new", 100));
+
+        let (expired, snapshot) = outbox.prune_expired(50);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "old");
+        assert!(snapshot.is_some());
+        assert_eq!(outbox.front(10001).unwrap().id, "new");
+        assert!(outbox.prune_expired(50).1.is_none());
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let (outbox, error) = Outbox::load(path.clone());
+        assert!(error.unwrap().contains("解析发件箱失败"));
+        assert!(outbox.list(0).is_empty());
+        assert!(!path.exists());
+
+        let backups: Vec<String> = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("outbox.json.") && backups[0].ends_with(".bak"));
+        assert_eq!(fs::read_to_string(dir.path().join(&backups[0])).unwrap(), "{ not json");
+    }
+}
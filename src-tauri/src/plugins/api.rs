@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 use serde::{Serialize, Deserialize};
 use reqwest::Client;
+use tokio::sync::OnceCell;
 use tokio::time::timeout;
 
 use crate::plugins::{PluginResult, PluginError};
+use crate::state::AppState;
 
 /// OneBot API响应结构
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,29 +62,70 @@ pub struct GroupMemberInfo {
     pub card_changeable: bool,
 }
 
+/// API 调用的传输方式
+#[allow(dead_code)]
+pub enum ApiTransport {
+    /// 通过 OneBot 实现的 HTTP API 调用
+    Http {
+        client: Client,
+        base_url: String,
+        /// HTTP API 所属的机器人账号，未指定时首次发送消息前通过 `get_login_info` 查询
+        self_id: OnceCell<i64>,
+    },
+    /// 通过当前活跃的 OneBot 连接发送动作，按 echo 关联响应
+    ///
+    /// `self_id` 为空时使用第一个在线的机器人。
+    Connection {
+        self_id: Option<i64>,
+    },
+}
+
 /// OneBot API客户端
 #[allow(dead_code)]
 pub struct OneBotApi {
     #[allow(dead_code)]
-    client: Client,
-    #[allow(dead_code)]
-    base_url: String,
+    transport: ApiTransport,
     #[allow(dead_code)]
     timeout: Duration,
     #[allow(dead_code)]
     retry_count: u32,
+    /// 所属的应用核心，用于发送限速与查找机器人连接
+    state: Weak<AppState>,
 }
 
 impl OneBotApi {
-    pub fn new(base_url: String) -> Self {
+    /// 创建通过 HTTP API 调用的客户端
+    ///
+    /// `self_id` 为该 HTTP API 所属的机器人账号，用于发送消息时排入对应的发送队列。
+    pub fn new(base_url: String, self_id: Option<i64>, state: Weak<AppState>) -> Self {
+        Self {
+            transport: ApiTransport::Http {
+                client: Client::new(),
+                base_url,
+                self_id: OnceCell::new_with(self_id),
+            },
+            timeout: Duration::from_secs(30),
+            retry_count: 3,
+            state,
+        }
+    }
+
+    /// 创建通过活跃 OneBot 连接调用的客户端
+    pub fn connection(self_id: Option<i64>, state: Weak<AppState>) -> Self {
         Self {
-            client: Client::new(),
-            base_url,
+            transport: ApiTransport::Connection { self_id },
             timeout: Duration::from_secs(30),
             retry_count: 3,
+            state,
         }
     }
 
+    /// 获取所属的应用核心
+    fn app_state(&self) -> PluginResult<Arc<AppState>> {
+        self.state.upgrade()
+            .ok_or_else(|| PluginError::ApiError("应用已关闭".to_string()))
+    }
+
     /// 设置超时时间
     #[allow(dead_code)]
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
@@ -88,7 +133,7 @@ impl OneBotApi {
         self
     }
 
-    /// 设置重试次数
+    /// 设置重试次数（仅对 HTTP 方式生效，连接方式发送后无法确认是否已执行，不做重试）
     #[allow(dead_code)]
     pub fn with_retry_count(mut self, retry_count: u32) -> Self {
         self.retry_count = retry_count;
@@ -101,10 +146,77 @@ impl OneBotApi {
         T: Serialize,
         R: for<'de> Deserialize<'de>,
     {
-        let url = format!("{}/{}", self.base_url, endpoint);
+        let data = match &self.transport {
+            ApiTransport::Http { client, base_url, self_id } => {
+                // 发送消息排在 HTTP API 所属机器人的发送队列中，无法确定账号时不经过发送队列
+                if crate::send_queue::is_send_action(endpoint) {
+                    match self.http_self_id(client, base_url, self_id).await {
+                        Ok(self_id) => {
+                            let params_map: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::to_value(params)?)
+                                .unwrap_or_default();
+                            crate::runtime::throttle_send(&*self.app_state()?, self_id, endpoint, &params_map).await
+                                .map_err(PluginError::ApiError)?;
+                        }
+                        Err(e) => eprintln!("无法确定 HTTP API 所属的机器人，本次发送不经过发送队列: {}", e),
+                    }
+                }
+                self.send_http_request(client, base_url, endpoint, params).await?
+            }
+            ApiTransport::Connection { self_id } => {
+                self.send_connection_request(*self_id, endpoint, params).await?
+            }
+        };
+
+        serde_json::from_value(data)
+            .map_err(|e| PluginError::ApiError(format!("解析响应失败: {}", e)))
+    }
+
+    /// HTTP API 所属的机器人账号，未指定时查询 `get_login_info` 并缓存
+    async fn http_self_id(&self, client: &Client, base_url: &str, self_id: &OnceCell<i64>) -> PluginResult<i64> {
+        self_id.get_or_try_init(|| async {
+            let data = self.send_http_request(client, base_url, "get_login_info", &serde_json::json!({})).await?;
+            data.get("user_id")
+                .and_then(|v| v.as_i64())
+                .ok_or_else(|| PluginError::ApiError("get_login_info 未返回 user_id".to_string()))
+        }).await.copied()
+    }
+
+    /// 通过活跃的 OneBot 连接发送动作
+    async fn send_connection_request<T>(&self, self_id: Option<i64>, endpoint: &str, params: &T) -> PluginResult<serde_json::Value>
+    where
+        T: Serialize,
+    {
+        let params: HashMap<String, serde_json::Value> = serde_json::from_value(serde_json::to_value(params)?)
+            .map_err(|e| PluginError::ApiError(format!("请求参数必须是对象: {}", e)))?;
+
+        let state = self.app_state()?;
+        let self_id = crate::runtime::resolve_bot_id(&state, self_id).await
+            .map_err(PluginError::ApiError)?;
+
+        // 超时只作用于请求本身，在发送队列中排队的时间不计入
+        let response = crate::runtime::send_onebot_api_request_with_timeout(&state, self_id, endpoint, params, Some(self.timeout)).await
+            .map_err(PluginError::ApiError)?;
+
+        if response.status == "ok" {
+            Ok(response.data.unwrap_or(serde_json::Value::Null))
+        } else {
+            Err(PluginError::ApiError(
+                response.message.unwrap_or_else(||
+                    format!("API调用失败，错误码: {}", response.retcode)
+                )
+            ))
+        }
+    }
+
+    /// 通过 HTTP API 发送动作，失败时按配置重试
+    async fn send_http_request<T>(&self, client: &Client, base_url: &str, endpoint: &str, params: &T) -> PluginResult<serde_json::Value>
+    where
+        T: Serialize,
+    {
+        let url = format!("{}/{}", base_url, endpoint);
         
         for attempt in 0..=self.retry_count {
-            let request = self.client
+            let request = client
                 .post(&url)
                 .json(params)
                 .timeout(self.timeout);
@@ -112,12 +224,11 @@ impl OneBotApi {
             match timeout(self.timeout, request.send()).await {
                 Ok(Ok(response)) => {
                     if response.status().is_success() {
-                        let onebot_response: OneBotResponse<R> = response.json().await
+                        let onebot_response: OneBotResponse<serde_json::Value> = response.json().await
                             .map_err(|e| PluginError::ApiError(format!("解析响应失败: {}", e)))?;
 
                         if onebot_response.status == "ok" {
-                            return onebot_response.data
-                                .ok_or_else(|| PluginError::ApiError("响应数据为空".to_string()));
+                            return Ok(onebot_response.data.unwrap_or(serde_json::Value::Null));
                         } else {
                             return Err(PluginError::ApiError(
                                 onebot_response.message.unwrap_or_else(|| 
@@ -523,9 +634,9 @@ pub struct NapCatApi {
 
 impl NapCatApi {
     #[allow(dead_code)]
-    pub fn new(base_url: String) -> Self {
+    pub fn new(base_url: String, state: Weak<AppState>) -> Self {
         Self {
-            onebot_api: OneBotApi::new(base_url),
+            onebot_api: OneBotApi::new(base_url, None, state),
         }
     }
 
@@ -5,6 +5,14 @@ use tokio::fs;
 
 use crate::plugins::{PluginResult, PluginError};
 
+/// 插件目录名，位于工作目录下
+pub const PLUGINS_DIR: &str = "plugins";
+
+/// 工作目录下的插件目录
+pub fn plugins_dir(work_dir: &Path) -> PathBuf {
+    work_dir.join(PLUGINS_DIR)
+}
+
 /// 全局插件配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalPluginConfig {
@@ -26,13 +34,16 @@ pub struct GlobalPluginConfig {
     pub security: SecurityConfig,
     /// 性能设置
     pub performance: PerformanceConfig,
+    /// OneBot HTTP API 地址，为空时插件通过活跃的 OneBot 连接调用 API
+    #[serde(default)]
+    pub api_url: Option<String>,
 }
 
 impl Default for GlobalPluginConfig {
     fn default() -> Self {
         Self {
             command_prefix: "/".to_string(),
-            plugins_dir: "plugins".to_string(),
+            plugins_dir: PLUGINS_DIR.to_string(),
             enabled: true,
             max_plugins: 50,
             plugin_timeout: 30,
@@ -40,14 +51,15 @@ impl Default for GlobalPluginConfig {
             log_level: "info".to_string(),
             security: SecurityConfig::default(),
             performance: PerformanceConfig::default(),
+            api_url: None,
         }
     }
 }
 
 impl GlobalPluginConfig {
-    /// 加载配置文件，如果不存在则创建默认配置
-    pub async fn load_or_default() -> PluginResult<Self> {
-        let config_path = Self::get_config_path();
+    /// 加载工作目录中的配置文件，如果不存在则创建默认配置
+    pub async fn load_or_default(work_dir: &Path) -> PluginResult<Self> {
+        let config_path = Self::get_config_path(work_dir);
         
         if config_path.exists() {
             Self::load_from_file(&config_path).await
@@ -79,14 +91,14 @@ impl GlobalPluginConfig {
         Ok(())
     }
 
-    /// 获取配置文件路径
-    pub fn get_config_path() -> PathBuf {
-        PathBuf::from("config").join("plugins.toml")
+    /// 获取工作目录中的配置文件路径
+    pub fn get_config_path(work_dir: &Path) -> PathBuf {
+        work_dir.join("config").join("plugins.toml")
     }
 
-    /// 保存当前配置
-    pub async fn save(&self) -> PluginResult<()> {
-        let config_path = Self::get_config_path();
+    /// 保存当前配置到工作目录
+    pub async fn save(&self, work_dir: &Path) -> PluginResult<()> {
+        let config_path = Self::get_config_path(work_dir);
         self.save_to_file(&config_path).await
     }
 
@@ -243,9 +255,9 @@ impl Default for PluginConfig {
 }
 
 impl PluginConfig {
-    /// 为指定插件加载配置
-    pub async fn load_for_plugin(plugin_name: &str) -> PluginResult<Self> {
-        let config_path = Self::get_plugin_config_path(plugin_name);
+    /// 从插件目录为指定插件加载配置
+    pub async fn load_for_plugin(plugins_dir: &Path, plugin_name: &str) -> PluginResult<Self> {
+        let config_path = Self::get_plugin_config_path(plugins_dir, plugin_name);
         
         if config_path.exists() {
             Self::load_from_file(&config_path).await
@@ -279,13 +291,13 @@ impl PluginConfig {
     }
 
     /// 获取插件配置文件路径
-    pub fn get_plugin_config_path(plugin_name: &str) -> PathBuf {
-        PathBuf::from("plugins").join(plugin_name).join("config.toml")
+    pub fn get_plugin_config_path(plugins_dir: &Path, plugin_name: &str) -> PathBuf {
+        plugins_dir.join(plugin_name).join("config.toml")
     }
 
-    /// 保存当前配置
-    pub async fn save(&self) -> PluginResult<()> {
-        let config_path = Self::get_plugin_config_path(&self.name);
+    /// 保存当前配置到插件目录
+    pub async fn save(&self, plugins_dir: &Path) -> PluginResult<()> {
+        let config_path = Self::get_plugin_config_path(plugins_dir, &self.name);
         self.save_to_file(&config_path).await
     }
 
@@ -388,14 +400,16 @@ impl Default for PluginLimits {
 /// 配置管理器
 #[allow(dead_code)]
 pub struct ConfigManager {
+    work_dir: PathBuf,
     global_config: GlobalPluginConfig,
     plugin_configs: HashMap<String, PluginConfig>,
 }
 
 impl ConfigManager {
     #[allow(dead_code)]
-    pub fn new() -> Self {
+    pub fn new(work_dir: PathBuf) -> Self {
         Self {
+            work_dir,
             global_config: GlobalPluginConfig::default(),
             plugin_configs: HashMap::new(),
         }
@@ -404,7 +418,7 @@ impl ConfigManager {
     /// 初始化配置管理器
     #[allow(dead_code)]
     pub async fn initialize(&mut self) -> PluginResult<()> {
-        self.global_config = GlobalPluginConfig::load_or_default().await?;
+        self.global_config = GlobalPluginConfig::load_or_default(&self.work_dir).await?;
         self.global_config.validate()?;
         Ok(())
     }
@@ -419,7 +433,7 @@ impl ConfigManager {
     #[allow(dead_code)]
     pub async fn update_global_config(&mut self, config: GlobalPluginConfig) -> PluginResult<()> {
         config.validate()?;
-        config.save().await?;
+        config.save(&self.work_dir).await?;
         self.global_config = config;
         Ok(())
     }
@@ -428,7 +442,7 @@ impl ConfigManager {
     #[allow(dead_code)]
     pub async fn get_plugin_config(&mut self, plugin_name: &str) -> PluginResult<&PluginConfig> {
         if !self.plugin_configs.contains_key(plugin_name) {
-            let config = PluginConfig::load_for_plugin(plugin_name).await?;
+            let config = PluginConfig::load_for_plugin(&plugins_dir(&self.work_dir), plugin_name).await?;
             self.plugin_configs.insert(plugin_name.to_string(), config);
         }
         
@@ -438,7 +452,7 @@ impl ConfigManager {
     /// 更新插件配置
     #[allow(dead_code)]
     pub async fn update_plugin_config(&mut self, config: PluginConfig) -> PluginResult<()> {
-        config.save().await?;
+        config.save(&plugins_dir(&self.work_dir)).await?;
         self.plugin_configs.insert(config.name.clone(), config);
         Ok(())
     }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Weak;
 use uuid::Uuid;
 
 use crate::plugins::{
@@ -10,6 +11,8 @@ use crate::plugins::message::ParsedMessage;
 use crate::plugins::command::CommandMatch;
 use crate::plugins::loader::PluginLoader;
 use crate::plugins::config::PluginConfig;
+use crate::onebot::{OneBotEvent, QuickOperation};
+use crate::state::AppState;
 
 /// 插件管理器
 pub struct PluginManager {
@@ -23,19 +26,30 @@ pub struct PluginManager {
     plugins_dir: PathBuf,
     /// 是否已初始化
     initialized: bool,
+    /// OneBot HTTP API 地址，为空时通过活跃的 OneBot 连接调用
+    api_url: Option<String>,
+    /// 所属的应用核心，插件通过它调用 OneBot API
+    app_state: Weak<AppState>,
 }
 
 impl PluginManager {
-    pub fn new() -> Self {
+    pub fn new(app_state: Weak<AppState>, plugins_dir: PathBuf) -> Self {
         Self {
             plugins: HashMap::new(),
             name_to_id: HashMap::new(),
             loader: PluginLoader::new(),
-            plugins_dir: PathBuf::from("plugins"),
+            plugins_dir,
             initialized: false,
+            api_url: None,
+            app_state,
         }
     }
 
+    /// 设置插件调用 OneBot API 的 HTTP 地址，传入 `None` 时改用活跃的 OneBot 连接
+    pub fn set_api_url(&mut self, api_url: Option<String>) {
+        self.api_url = api_url.filter(|url| !url.trim().is_empty());
+    }
+
     /// 初始化插件管理器
     pub async fn initialize(&mut self) -> PluginResult<()> {
         if self.initialized {
@@ -110,7 +124,7 @@ impl PluginManager {
         }
 
         // 加载插件配置
-        let config = PluginConfig::load_for_plugin(&info.name).await
+        let config = PluginConfig::load_for_plugin(&self.plugins_dir, &info.name).await
             .unwrap_or_else(|_| PluginConfig::default());
 
         // 创建插件实例
@@ -147,7 +161,7 @@ impl PluginManager {
 
         if let Some(plugin) = plugin_arc {
             // 创建插件上下文
-            let context = self.create_plugin_context(&plugin_name, &plugin_config).await?;
+            let context = self.create_plugin_context(&plugin_name, &plugin_config, None).await?;
 
             // 调用插件生命周期方法
             plugin.on_init(&context).await?;
@@ -182,7 +196,7 @@ impl PluginManager {
 
         if let Some(plugin) = plugin_arc {
             // 创建插件上下文
-            let context = self.create_plugin_context(&plugin_name, &plugin_config).await?;
+            let context = self.create_plugin_context(&plugin_name, &plugin_config, None).await?;
 
             // 调用插件生命周期方法
             plugin.on_stop(&context).await?;
@@ -218,7 +232,7 @@ impl PluginManager {
 
         if let Some(plugin) = plugin_arc {
             // 创建插件上下文
-            let context = self.create_plugin_context(&plugin_name, &plugin_config).await?;
+            let context = self.create_plugin_context(&plugin_name, &plugin_config, None).await?;
 
             // 调用插件卸载方法
             plugin.on_unload(&context).await?;
@@ -231,6 +245,16 @@ impl PluginManager {
         Ok(())
     }
 
+    /// 卸载所有插件，退出前调用以便插件执行清理
+    pub async fn unload_all_plugins(&mut self) {
+        let plugin_ids: Vec<Uuid> = self.plugins.keys().cloned().collect();
+        for plugin_id in plugin_ids {
+            if let Err(e) = self.unload_plugin(&plugin_id).await {
+                eprintln!("卸载插件 {} 失败: {}", plugin_id, e);
+            }
+        }
+    }
+
     /// 重新加载插件
     #[allow(dead_code)]
     pub async fn reload_plugin(&mut self, plugin_id: &Uuid) -> PluginResult<()> {
@@ -247,7 +271,6 @@ impl PluginManager {
     }
 
     /// 处理消息
-    #[allow(dead_code)]
     pub async fn handle_message(&self, message: &ParsedMessage) -> PluginResult<()> {
         // 按优先级排序插件
         let mut sorted_plugins: Vec<_> = self.plugins.values()
@@ -263,7 +286,7 @@ impl PluginManager {
         for instance in sorted_plugins {
             if let Some(plugin) = &instance.plugin {
                 if plugin.should_handle_message(message).await {
-                    let context = self.create_plugin_context(&instance.info.name, &instance.config).await?;
+                    let context = self.create_plugin_context(&instance.info.name, &instance.config, Some(message.self_id)).await?;
 
                     match plugin.handle_message(&context, message).await {
                         Ok(_handled) => {
@@ -281,15 +304,102 @@ impl PluginManager {
         Ok(())
     }
 
+    /// 处理机器人自身发出的消息，仅分发给实现了 `handle_message_sent` 的插件
+    pub async fn handle_message_sent(&self, message: &ParsedMessage) -> PluginResult<()> {
+        let mut sorted_plugins: Vec<_> = self.plugins.values()
+            .filter(|instance| instance.can_process_messages())
+            .collect();
+
+        sorted_plugins.sort_by_key(|instance| {
+            instance.plugin.as_ref()
+                .map(|p| p.get_priority())
+                .unwrap_or(999)
+        });
+
+        for instance in sorted_plugins {
+            if let Some(plugin) = &instance.plugin {
+                let context = self.create_plugin_context(&instance.info.name, &instance.config, Some(message.self_id)).await?;
+
+                if let Err(e) = plugin.handle_message_sent(&context, message).await {
+                    eprintln!("插件 {} 处理自身消息时出错: {}", instance.info.name, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 处理通知、请求和元事件
+    pub async fn handle_event(&self, event: &OneBotEvent) -> PluginResult<()> {
+
+        // 按优先级排序插件
+        let mut sorted_plugins: Vec<_> = self.plugins.values()
+            .filter(|instance| instance.can_process_messages())
+            .collect();
+
+        sorted_plugins.sort_by_key(|instance| {
+            instance.plugin.as_ref()
+                .map(|p| p.get_priority())
+                .unwrap_or(999)
+        });
+
+        for instance in sorted_plugins {
+            if let Some(plugin) = &instance.plugin {
+                let context = self.create_plugin_context(&instance.info.name, &instance.config, Some(event.self_id())).await?;
+
+                let result = match event {
+                    OneBotEvent::Notice { notice, .. } => plugin.handle_notice(&context, notice).await,
+                    OneBotEvent::Request { request, .. } => plugin.handle_request(&context, request).await,
+                    OneBotEvent::MetaEvent { .. } => plugin.handle_meta_event(&context, &serde_json::to_value(event)?).await,
+                    OneBotEvent::Message { .. } | OneBotEvent::MessageSent { .. } => Ok(false),
+                };
+
+                if let Err(e) = result {
+                    eprintln!("插件 {} 处理事件时出错: {}", instance.info.name, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 按优先级询问插件，返回第一个给出的快速操作
+    pub async fn quick_operation(&self, event: &OneBotEvent) -> PluginResult<Option<QuickOperation>> {
+        let event_value = serde_json::to_value(event)?;
+
+        let mut sorted_plugins: Vec<_> = self.plugins.values()
+            .filter(|instance| instance.can_process_messages())
+            .collect();
+
+        sorted_plugins.sort_by_key(|instance| {
+            instance.plugin.as_ref()
+                .map(|p| p.get_priority())
+                .unwrap_or(999)
+        });
+
+        for instance in sorted_plugins {
+            if let Some(plugin) = &instance.plugin {
+                let context = self.create_plugin_context(&instance.info.name, &instance.config, Some(event.self_id())).await?;
+
+                match plugin.quick_operation(&context, &event_value).await {
+                    Ok(Some(operation)) => return Ok(Some(operation)),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("插件 {} 生成快速操作时出错: {}", instance.info.name, e),
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     /// 处理命令
-    #[allow(dead_code)]
     pub async fn handle_command(&self, command: &CommandMatch, message: &ParsedMessage) -> PluginResult<()> {
         // 找到匹配的插件
         for instance in self.plugins.values() {
             if instance.can_process_messages() {
                 if let Some(plugin) = &instance.plugin {
                     if plugin.should_handle_command(command).await {
-                        let context = self.create_plugin_context(&instance.info.name, &instance.config).await?;
+                        let context = self.create_plugin_context(&instance.info.name, &instance.config, Some(message.self_id)).await?;
                         plugin.handle_command(&context, command, message).await?;
                         break; // 只让第一个匹配的插件处理
                     }
@@ -301,7 +411,9 @@ impl PluginManager {
     }
 
     /// 创建插件上下文
-    async fn create_plugin_context(&self, plugin_name: &str, config: &PluginConfig) -> PluginResult<PluginContext> {
+    ///
+    /// `self_id` 为触发本次调用的机器人账号，插件通过上下文调用 API 时会发往该机器人。
+    async fn create_plugin_context(&self, plugin_name: &str, config: &PluginConfig, self_id: Option<i64>) -> PluginResult<PluginContext> {
         use crate::plugins::api::OneBotApi;
         use crate::plugins::logger::DefaultPluginLogger;
         use std::sync::Arc;
@@ -312,8 +424,11 @@ impl PluginManager {
             std::fs::create_dir_all(&data_dir)?;
         }
 
-        // 创建API实例（这里需要传入实际的OneBot连接信息）
-        let api = Arc::new(OneBotApi::new("http://localhost:3000".to_string()));
+        // 创建API实例：配置了 HTTP 地址时走 HTTP，否则通过活跃的 OneBot 连接发送
+        let api = Arc::new(match &self.api_url {
+            Some(url) => OneBotApi::new(url.clone(), self_id, self.app_state.clone()),
+            None => OneBotApi::connection(self_id, self.app_state.clone()),
+        });
 
         // 创建日志记录器
         let logger = Arc::new(DefaultPluginLogger::new());
@@ -326,6 +441,20 @@ impl PluginManager {
         ))
     }
 
+    /// 直接注册一个已创建的插件实例并置为运行状态，供测试使用
+    #[cfg(test)]
+    pub(crate) fn register_running_plugin(&mut self, plugin: std::sync::Arc<dyn crate::plugins::Plugin + Send + Sync>) -> Uuid {
+        let info = plugin.get_info();
+        let mut instance = PluginInstance::new(info.clone(), PluginConfig::default());
+        instance.plugin = Some(plugin);
+        instance.status = PluginStatus::Running;
+
+        let plugin_id = instance.id;
+        self.plugins.insert(plugin_id, instance);
+        self.name_to_id.insert(info.name, plugin_id);
+        plugin_id
+    }
+
     /// 获取所有插件信息
     pub fn get_all_plugins(&self) -> Vec<PluginMetadata> {
         self.plugins.values()
@@ -4,6 +4,7 @@ use regex::Regex;
 use lazy_static::lazy_static;
 
 use crate::plugins::{PluginResult, PluginError};
+use crate::onebot::{MessageContent, MessageSegment};
 
 /// CQ码类型枚举
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -196,7 +197,7 @@ impl CQCode {
     pub fn to_cq_string(&self) -> String {
         match &self.code_type {
             CQCodeType::Text => {
-                self.text.as_ref().unwrap_or(&String::new()).clone()
+                escape_cq_text(self.text.as_deref().unwrap_or_default())
             }
             _ => {
                 let type_str = self.code_type.to_string();
@@ -213,6 +214,47 @@ impl CQCode {
     }
 }
 
+impl From<&MessageSegment> for CQCode {
+    fn from(segment: &MessageSegment) -> Self {
+        match segment {
+            MessageSegment::Text { text } => CQCode::text(text),
+            _ => {
+                let (seg_type, params) = segment.to_parts();
+                Self {
+                    code_type: CQCodeType::from(seg_type.as_str()),
+                    params,
+                    text: None,
+                }
+            }
+        }
+    }
+}
+
+impl From<&CQCode> for MessageSegment {
+    fn from(code: &CQCode) -> Self {
+        match &code.code_type {
+            CQCodeType::Text => MessageSegment::text(code.text.as_deref().unwrap_or_default()),
+            code_type => MessageSegment::from_parts(&code_type.to_string(), code.params.clone()),
+        }
+    }
+}
+
+/// 转义CQ码字符串中的纯文本
+fn escape_cq_text(text: &str) -> String {
+    text
+        .replace("&", "&amp;")
+        .replace("[", "&#91;")
+        .replace("]", "&#93;")
+}
+
+/// 反转义CQ码字符串中的纯文本
+fn unescape_cq_text(text: &str) -> String {
+    text
+        .replace("&#93;", "]")
+        .replace("&#91;", "[")
+        .replace("&amp;", "&")
+}
+
 /// 转义CQ码参数
 fn escape_cq_param(param: &str) -> String {
     param
@@ -253,7 +295,7 @@ impl MessageParser {
             if start > last_end {
                 let text = &message[last_end..start];
                 if !text.is_empty() {
-                    codes.push(CQCode::text(text));
+                    codes.push(CQCode::text(&unescape_cq_text(text)));
                 }
             }
 
@@ -285,22 +327,36 @@ impl MessageParser {
         if last_end < message.len() {
             let text = &message[last_end..];
             if !text.is_empty() {
-                codes.push(CQCode::text(text));
+                codes.push(CQCode::text(&unescape_cq_text(text)));
             }
         }
 
-        // 如果没有找到任何CQ码，整个消息就是纯文本
-        if codes.is_empty() && !message.is_empty() {
-            codes.push(CQCode::text(message));
-        }
-
         Ok(codes)
     }
 
-    /// 解析OneBot事件
+    /// 解析OneBot事件（包括机器人自身发出的消息）
     pub fn parse_onebot_event(event: &crate::onebot::OneBotEvent) -> PluginResult<ParsedMessage> {
+        let target_id = match event {
+            crate::onebot::OneBotEvent::MessageSent { target_id, .. } => *target_id,
+            _ => None,
+        };
+
         match event {
             crate::onebot::OneBotEvent::Message {
+                self_id,
+                message_id,
+                message_type,
+                sub_type,
+                user_id,
+                group_id,
+                raw_message,
+                message,
+                sender,
+                time,
+                ..
+            }
+            | crate::onebot::OneBotEvent::MessageSent {
+                self_id,
                 message_id,
                 message_type,
                 sub_type,
@@ -312,32 +368,50 @@ impl MessageParser {
                 time,
                 ..
             } => {
-                // 将message转换为字符串
-                let message_str = if let Some(msg_str) = message.as_str() {
-                    msg_str
-                } else {
-                    &message.to_string()
+                // string 格式直接解析CQ码，array 格式逐段转换并拼接为CQ码字符串
+                let (message_str, cq_codes) = match message {
+                    MessageContent::Text(text) => (text.clone(), Self::parse_cq_codes(text)?),
+                    MessageContent::Segments(segments) => {
+                        let cq_codes = Self::segments_to_cq_codes(segments);
+                        (Self::cq_codes_to_string(&cq_codes), cq_codes)
+                    }
                 };
 
-                let cq_codes = Self::parse_cq_codes(message_str)?;
-
                 Ok(ParsedMessage {
+                    self_id: *self_id,
                     message_id: *message_id,
                     message_type: message_type.clone(),
                     sub_type: sub_type.clone(),
                     user_id: *user_id,
                     group_id: *group_id,
                     raw_message: raw_message.clone(),
-                    message: message_str.to_string(),
+                    message: message_str,
                     cq_codes,
                     sender: serde_json::to_value(sender).unwrap_or_default(),
                     time: *time,
+                    target_id,
                 })
             }
             _ => Err(PluginError::MessageParseError("不支持的事件类型".to_string()))
         }
     }
 
+    /// 将消息段转换为CQ码
+    pub fn segments_to_cq_codes(segments: &[MessageSegment]) -> Vec<CQCode> {
+        segments.iter().map(CQCode::from).collect()
+    }
+
+    /// 将CQ码转换为消息段
+    #[allow(dead_code)]
+    pub fn cq_codes_to_segments(cq_codes: &[CQCode]) -> Vec<MessageSegment> {
+        cq_codes.iter().map(MessageSegment::from).collect()
+    }
+
+    /// 将CQ码拼接为CQ码字符串
+    pub fn cq_codes_to_string(cq_codes: &[CQCode]) -> String {
+        cq_codes.iter().map(CQCode::to_cq_string).collect()
+    }
+
     /// 提取纯文本内容
     pub fn extract_plain_text(cq_codes: &[CQCode]) -> String {
         cq_codes.iter()
@@ -371,6 +445,9 @@ impl MessageParser {
 /// 解析后的消息结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParsedMessage {
+    /// 收到消息的机器人账号
+    #[serde(default)]
+    pub self_id: i64,
     pub message_id: i64,
     pub message_type: String,
     pub sub_type: String,
@@ -381,6 +458,9 @@ pub struct ParsedMessage {
     pub cq_codes: Vec<CQCode>,
     pub sender: serde_json::Value,
     pub time: i64,
+    /// 机器人自身发出的私聊消息的接收者
+    #[serde(default)]
+    pub target_id: Option<i64>,
 }
 
 impl ParsedMessage {
@@ -716,3 +796,139 @@ impl MessageValidator {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onebot::OneBotEvent;
+    use serde_json::json;
+
+    fn segment(value: serde_json::Value) -> MessageSegment {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// 消息段 -> CQ码 -> CQ码字符串 -> CQ码 -> 消息段，每一步都不应丢失内容
+    fn assert_round_trip(original: MessageSegment) {
+        let code = CQCode::from(&original);
+        assert_eq!(MessageSegment::from(&code), original);
+
+        let parsed = MessageParser::parse_cq_codes(&code.to_cq_string()).unwrap();
+        assert_eq!(parsed, vec![code.clone()], "{}", code.to_cq_string());
+        assert_eq!(MessageSegment::from(&parsed[0]), original);
+    }
+
+    #[test]
+    fn text_round_trip_escapes_special_characters() {
+        let original = MessageSegment::text("a&b [c], d");
+        let code = CQCode::from(&original);
+        assert_eq!(code.to_cq_string(), "a&amp;b &#91;c&#93;, d");
+        assert_round_trip(original);
+    }
+
+    #[test]
+    fn at_round_trip() {
+        let numeric = segment(json!({ "type": "at", "data": { "qq": 123456 } }));
+        assert_eq!(CQCode::from(&numeric), CQCode::at(123456));
+        assert_round_trip(numeric);
+
+        let all = segment(json!({ "type": "at", "data": { "qq": "all" } }));
+        assert_eq!(CQCode::from(&all), CQCode::at_all());
+        assert_round_trip(all);
+    }
+
+    #[test]
+    fn media_round_trip() {
+        let image = segment(json!({ "type": "image", "data": { "file": "a.jpg", "url": "https://example.com/a.jpg?x=1&y=[2]" } }));
+        assert!(matches!(image, MessageSegment::Image { .. }));
+        assert_round_trip(image);
+
+        let record = segment(json!({ "type": "record", "data": { "file": "a.amr" } }));
+        assert_eq!(CQCode::from(&record), CQCode::record("a.amr"));
+        assert_round_trip(record);
+
+        let video = segment(json!({ "type": "video", "data": { "file": "a.mp4" } }));
+        assert!(matches!(video, MessageSegment::Video { .. }));
+        assert_round_trip(video);
+
+        let file = segment(json!({ "type": "file", "data": { "file": "a.zip", "name": "a,b.zip" } }));
+        assert!(matches!(file, MessageSegment::File { .. }));
+        assert_round_trip(file);
+    }
+
+    #[test]
+    fn id_segments_round_trip() {
+        let face = segment(json!({ "type": "face", "data": { "id": 14 } }));
+        assert_eq!(CQCode::from(&face), CQCode::face(14));
+        assert_round_trip(face);
+
+        let reply = segment(json!({ "type": "reply", "data": { "id": "-2147483648" } }));
+        assert_eq!(CQCode::from(&reply).get_param("id").map(String::as_str), Some("-2147483648"));
+        assert_round_trip(reply);
+
+        let forward = segment(json!({ "type": "forward", "data": { "id": "fwd_1" } }));
+        assert!(matches!(forward, MessageSegment::Forward { .. }));
+        assert_eq!(CQCode::from(&forward).code_type, CQCodeType::Forward);
+        assert_round_trip(forward);
+    }
+
+    #[test]
+    fn json_round_trip_escapes_params() {
+        let data = r#"{"app":"com.tencent.miniapp","list":[1,2],"desc":"a&b"}"#;
+        let original = segment(json!({ "type": "json", "data": { "data": data } }));
+        assert!(matches!(original, MessageSegment::Json { .. }));
+
+        let cq = CQCode::from(&original).to_cq_string();
+        assert!(!cq[1..cq.len() - 1].contains(['[', ']']), "{}", cq);
+        assert!(cq.contains("&#44;"));
+        assert_round_trip(original);
+    }
+
+    #[test]
+    fn unknown_type_round_trip() {
+        let original = segment(json!({ "type": "markdown", "data": { "content": "# 标题" } }));
+        assert!(matches!(original, MessageSegment::Other { .. }));
+
+        let code = CQCode::from(&original);
+        assert_eq!(code.code_type, CQCodeType::Custom("markdown".to_string()));
+        assert_round_trip(original);
+    }
+
+    #[test]
+    fn string_and_array_formats_parse_to_same_cq_codes() {
+        let event = |message: serde_json::Value| -> OneBotEvent {
+            serde_json::from_value(json!({
+                "post_type": "message",
+                "time": 1700000000,
+                "self_id": 10001,
+                "message_type": "group",
+                "sub_type": "normal",
+                "message_id": 1,
+                "group_id": 20002,
+                "user_id": 30003,
+                "message": message,
+                "raw_message": "",
+                "font": 0,
+                "sender": { "user_id": 30003, "nickname": "小明" },
+            })).unwrap()
+        };
+
+        let string_format = event(json!(
+            "[CQ:reply,id=99]你好 &#91;世界&#93; &amp; [CQ:at,qq=123456][CQ:at,qq=all][CQ:face,id=14][CQ:image,file=a.jpg]"
+        ));
+        let array_format = event(json!([
+            { "type": "reply", "data": { "id": "99" } },
+            { "type": "text", "data": { "text": "你好 [世界] & " } },
+            { "type": "at", "data": { "qq": 123456 } },
+            { "type": "at", "data": { "qq": "all" } },
+            { "type": "face", "data": { "id": 14 } },
+            { "type": "image", "data": { "file": "a.jpg" } },
+        ]));
+
+        let from_string = MessageParser::parse_onebot_event(&string_format).unwrap();
+        let from_array = MessageParser::parse_onebot_event(&array_format).unwrap();
+        assert_eq!(from_string.cq_codes, from_array.cq_codes);
+        assert_eq!(from_string.get_plain_text(), "你好 [世界] & ");
+        assert!(from_array.is_at_bot(123456));
+        assert!(from_array.is_at_all());
+    }
+}
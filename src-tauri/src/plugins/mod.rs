@@ -8,11 +8,14 @@ pub mod loader;
 pub mod security;
 pub mod logger;
 
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
 use tokio::sync::RwLock;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
+use crate::state::AppState;
+
 pub use manager::PluginManager;
 pub use plugin_trait::{Plugin, PluginInfo, PluginContext, PluginMetadata};
 pub use config::{PluginConfig, GlobalPluginConfig};
@@ -166,32 +169,36 @@ pub struct PluginSystem {
     pub onebot_api: Arc<OneBotApi>,
     /// 全局配置
     pub global_config: Arc<RwLock<GlobalPluginConfig>>,
+    /// 工作目录，`config/plugins.toml` 与插件目录位于其中
+    work_dir: PathBuf,
 }
 
 impl PluginSystem {
-    pub fn new(onebot_api: Arc<OneBotApi>) -> Self {
+    pub fn new(app_state: Weak<AppState>, work_dir: PathBuf) -> Self {
         Self {
-            manager: Arc::new(RwLock::new(PluginManager::new())),
+            manager: Arc::new(RwLock::new(PluginManager::new(app_state.clone(), config::plugins_dir(&work_dir)))),
             command_manager: Arc::new(RwLock::new(CommandManager::new())),
-            onebot_api,
+            onebot_api: Arc::new(OneBotApi::connection(None, app_state)),
             global_config: Arc::new(RwLock::new(GlobalPluginConfig::default())),
+            work_dir,
         }
     }
 
     /// 初始化插件系统
     pub async fn initialize(&self) -> PluginResult<()> {
         // 创建插件目录
-        let plugins_dir = std::path::Path::new("plugins");
+        let plugins_dir = config::plugins_dir(&self.work_dir);
         if !plugins_dir.exists() {
-            std::fs::create_dir_all(plugins_dir)?;
+            std::fs::create_dir_all(&plugins_dir)?;
         }
 
         // 加载全局配置
         let mut global_config = self.global_config.write().await;
-        *global_config = GlobalPluginConfig::load_or_default().await?;
+        *global_config = GlobalPluginConfig::load_or_default(&self.work_dir).await?;
 
         // 初始化插件管理器
         let mut manager = self.manager.write().await;
+        manager.set_api_url(global_config.api_url.clone());
         manager.initialize().await?;
 
         // 初始化命令管理器
@@ -201,10 +208,21 @@ impl PluginSystem {
         Ok(())
     }
 
-    /// 处理OneBot消息
-    #[allow(dead_code)]
+    /// 处理OneBot事件
     pub async fn handle_message(&self, message: &crate::onebot::OneBotEvent) -> PluginResult<()> {
         let manager = self.manager.read().await;
+
+        // 自身发出的消息不参与命令匹配，避免机器人响应自己
+        if matches!(message, crate::onebot::OneBotEvent::MessageSent { .. }) {
+            let parsed_message = MessageParser::parse_onebot_event(message)?;
+            return manager.handle_message_sent(&parsed_message).await;
+        }
+
+        // 通知、请求和元事件交给事件处理接口
+        if !matches!(message, crate::onebot::OneBotEvent::Message { .. }) {
+            return manager.handle_event(message).await;
+        }
+
         let cmd_manager = self.command_manager.read().await;
 
         // 解析消息
@@ -221,11 +239,98 @@ impl PluginSystem {
 
         Ok(())
     }
+
+    /// 为 HTTP POST 上报的事件获取快速操作
+    pub async fn quick_operation(&self, event: &crate::onebot::OneBotEvent) -> PluginResult<Option<crate::onebot::QuickOperation>> {
+        let manager = self.manager.read().await;
+        manager.quick_operation(event).await
+    }
 }
 
-/// 插件系统初始化函数
-pub async fn init_plugin_system(onebot_api: Arc<OneBotApi>) -> PluginResult<Arc<PluginSystem>> {
-    let system = Arc::new(PluginSystem::new(onebot_api));
+/// 插件系统初始化函数，插件与插件配置从 `work_dir` 中加载
+pub async fn init_plugin_system(app_state: Weak<AppState>, work_dir: PathBuf) -> PluginResult<Arc<PluginSystem>> {
+    let system = Arc::new(PluginSystem::new(app_state, work_dir));
     system.initialize().await?;
     Ok(system)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::plugin_trait::{CommandHandler, EventHandler, MessageHandler, PluginLifecycle};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 记录各处理接口被调用次数的插件
+    #[derive(Default)]
+    struct RecordingPlugin {
+        messages: AtomicUsize,
+        commands: AtomicUsize,
+        sent: AtomicUsize,
+    }
+
+    impl PluginLifecycle for RecordingPlugin {}
+    impl EventHandler for RecordingPlugin {}
+
+    #[async_trait]
+    impl MessageHandler for RecordingPlugin {
+        async fn handle_message(&self, _context: &PluginContext, _message: &message::ParsedMessage) -> PluginResult<bool> {
+            self.messages.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+
+        async fn handle_message_sent(&self, _context: &PluginContext, _message: &message::ParsedMessage) -> PluginResult<bool> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler for RecordingPlugin {
+        async fn handle_command(&self, _context: &PluginContext, _command: &command::CommandMatch, _message: &message::ParsedMessage) -> PluginResult<bool> {
+            self.commands.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    impl Plugin for RecordingPlugin {
+        fn get_info(&self) -> PluginInfo {
+            PluginInfo { name: "recording".to_string(), ..PluginInfo::default() }
+        }
+    }
+
+    fn private_message(post_type: &str) -> crate::onebot::OneBotEvent {
+        serde_json::from_value(json!({
+            "time": 1700000000,
+            "self_id": 10001,
+            "post_type": post_type,
+            "message_type": "private",
+            "sub_type": "friend",
+            "message_id": 1,
+            "user_id": 10001,
+            "target_id": 30003,
+            "message": "hello",
+            "raw_message": "hello",
+            "font": 0,
+            "sender": { "user_id": 10001, "nickname": "bot" },
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn message_sent_is_not_delivered_as_normal_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = PluginSystem::new(Weak::new(), dir.path().to_path_buf());
+        let plugin = Arc::new(RecordingPlugin::default());
+        system.manager.write().await.register_running_plugin(plugin.clone());
+
+        system.handle_message(&private_message("message_sent")).await.unwrap();
+        assert_eq!(plugin.sent.load(Ordering::SeqCst), 1);
+        assert_eq!(plugin.messages.load(Ordering::SeqCst), 0);
+        assert_eq!(plugin.commands.load(Ordering::SeqCst), 0);
+
+        system.handle_message(&private_message("message")).await.unwrap();
+        assert_eq!(plugin.sent.load(Ordering::SeqCst), 1);
+        assert_eq!(plugin.messages.load(Ordering::SeqCst), 1);
+    }
+}
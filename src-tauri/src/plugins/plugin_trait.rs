@@ -5,6 +5,7 @@ use serde::{Serialize, Deserialize};
 use crate::plugins::{PluginResult, OneBotApi};
 use crate::plugins::message::ParsedMessage;
 use crate::plugins::command::CommandMatch;
+use crate::onebot::{NoticeEvent, QuickOperation, RequestEvent};
 
 /// 插件信息结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,6 +167,15 @@ pub trait MessageHandler {
     ) -> PluginResult<bool> {
         self.handle_message(context, message).await
     }
+
+    /// 处理机器人自身发出的消息（message_sent），需要的插件覆盖此方法即可启用
+    async fn handle_message_sent(
+        &self,
+        _context: &PluginContext,
+        _message: &ParsedMessage,
+    ) -> PluginResult<bool> {
+        Ok(false)
+    }
 }
 
 /// 命令处理接口
@@ -195,20 +205,20 @@ pub trait CommandHandler {
 #[async_trait]
 #[allow(dead_code)]
 pub trait EventHandler {
-    /// 处理通知事件
+    /// 处理通知事件，可直接匹配 `NoticeEvent` 的具体类型
     async fn handle_notice(
         &self,
         _context: &PluginContext,
-        _notice: &serde_json::Value,
+        _notice: &NoticeEvent,
     ) -> PluginResult<bool> {
         Ok(false)
     }
 
-    /// 处理请求事件
+    /// 处理请求事件，可直接匹配 `RequestEvent` 的具体类型
     async fn handle_request(
         &self,
         _context: &PluginContext,
-        _request: &serde_json::Value,
+        _request: &RequestEvent,
     ) -> PluginResult<bool> {
         Ok(false)
     }
@@ -221,6 +231,15 @@ pub trait EventHandler {
     ) -> PluginResult<bool> {
         Ok(false)
     }
+
+    /// 为 HTTP POST 上报的事件提供快速操作，返回 `None` 表示不处理
+    async fn quick_operation(
+        &self,
+        _context: &PluginContext,
+        _event: &serde_json::Value,
+    ) -> PluginResult<Option<QuickOperation>> {
+        Ok(None)
+    }
 }
 
 /// 插件主接口，所有插件都必须实现此trait
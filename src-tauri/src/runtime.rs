@@ -0,0 +1,2038 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+
+use crate::admin_api;
+use crate::config::{AppSettings, ConfigManager, LogEntry, LogLevel, ServerConfig};
+use crate::event_bus;
+use crate::http_post;
+use crate::log_storage;
+use crate::onebot::{OneBotEvent, ConnectionStatus, ConnectionMode, BotAccount, Friend, Group, OneBotApiRequest, OneBotApiResponse, BotLoginInfo, SendMessageResponse, extract_plain_text, format_event_log};
+use crate::outbox;
+use crate::plugins;
+use crate::send_queue;
+use crate::state::{AppState, BotHeartbeat};
+use crate::websocket_server::{self, OneBotServer, CONNECTION_CLOSED};
+
+// API 调用缓存时间（秒）
+pub const CACHE_DURATION: i64 = 300; // 5分钟
+
+/// 服务器运行状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerRuntimeStatus {
+    pub server_id: String,
+    pub is_running: bool,
+    pub status: String, // "disconnected", "connecting", "listening", "connected"
+    pub connection_count: u32,
+    pub bots: Vec<i64>,
+    pub subscribers: Vec<event_bus::SubscriberStats>, // 事件总线各订阅者的接收与丢弃统计
+    pub events: event_bus::EventCounts, // 按类型统计的事件数
+}
+
+/// 机器人状态变化事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotStatusChange {
+    pub self_id: i64,
+    pub status: String, // "online", "degraded", "offline"
+    pub previous_status: String,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+/// 聊天消息事件，供前端聊天窗口显示收发的消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub self_id: i64,
+    pub message_id: i64,
+    pub message_type: String, // "private", "group"
+    pub group_id: Option<i64>,
+    pub peer_id: i64, // 私聊对方的 QQ 号，群聊时为发送者
+    pub sender_name: String,
+    pub content: String,
+    pub outgoing: bool, // 是否为机器人自身发出的消息
+    pub time: i64,
+}
+
+/// 将消息事件推送给前端聊天窗口
+pub fn emit_chat_message(state: &AppState, event: &OneBotEvent) {
+    let chat_message = match event {
+        OneBotEvent::Message { self_id, message_id, message_type, group_id, user_id, sender, message, time, .. } => ChatMessage {
+            self_id: *self_id,
+            message_id: *message_id,
+            message_type: message_type.clone(),
+            group_id: *group_id,
+            peer_id: *user_id,
+            sender_name: sender.card.clone().filter(|card| !card.is_empty()).unwrap_or_else(|| sender.nickname.clone()),
+            content: extract_plain_text(message),
+            outgoing: false,
+            time: *time,
+        },
+        OneBotEvent::MessageSent { self_id, message_id, message_type, group_id, user_id, target_id, sender, message, time, .. } => ChatMessage {
+            self_id: *self_id,
+            message_id: *message_id,
+            message_type: message_type.clone(),
+            group_id: *group_id,
+            peer_id: target_id.unwrap_or(*user_id),
+            sender_name: sender.nickname.clone(),
+            content: extract_plain_text(message),
+            outgoing: true,
+            time: *time,
+        },
+        _ => return,
+    };
+
+    state.emit("chat-message", &chat_message);
+}
+
+/// 超过心跳间隔的 1.5 倍未收到事件视为降级
+pub const HEARTBEAT_DEGRADED_FACTOR: f64 = 1.5;
+
+/// 超过心跳间隔的 3 倍未收到事件视为离线
+pub const HEARTBEAT_OFFLINE_FACTOR: f64 = 3.0;
+
+/// 两次获取机器人昵称之间的最短间隔，避免获取失败时每个事件都调用一次 API
+const NICKNAME_RETRY_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60);
+
+/// 机器人在线但重放发件箱失败时，再次重放前等待的时间
+const OUTBOX_RETRY_DELAY: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
+/// 更新机器人状态，状态发生变化时记录日志、推送前端事件并通知插件
+pub async fn update_bot_status(state: &Arc<AppState>, bot_id: i64, status: &str, reason: &str) {
+    let previous_status = {
+        let mut accounts = state.bot_accounts.lock().await;
+        let account = accounts.entry(bot_id).or_insert_with(|| BotAccount {
+            self_id: bot_id,
+            nickname: format!("Bot {}", bot_id),
+            status: "offline".to_string(),
+            friends: Vec::new(),
+            groups: Vec::new(),
+            friends_updated: 0,
+            groups_updated: 0,
+        });
+
+        if account.status == status {
+            return;
+        }
+        std::mem::replace(&mut account.status, status.to_string())
+    };
+
+    let change = BotStatusChange {
+        self_id: bot_id,
+        status: status.to_string(),
+        previous_status,
+        reason: reason.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+
+    let (level, tag) = if status == "online" { (LogLevel::Info, "INFO") } else { (LogLevel::Warning, "WARN") };
+    state.logs.add(LogEntry::new(
+        level,
+        "bot".to_string(),
+        format!("[{}] 机器人 {} 状态: {} -> {} ({})", tag, bot_id, change.previous_status, change.status, reason),
+        serde_json::to_value(&change).ok(),
+    ));
+
+    state.emit("bot-status", &change);
+
+    // 机器人重新上线后重放发件箱
+    if status == "online" {
+        tokio::spawn(replay_outbox(Arc::clone(state), bot_id));
+    }
+
+    // 以元事件的形式通知插件
+    let system = state.plugin_system().await;
+    if let Some(system) = system {
+        let mut extra = HashMap::new();
+        extra.insert("previous_status".to_string(), serde_json::Value::String(change.previous_status.clone()));
+        extra.insert("reason".to_string(), serde_json::Value::String(change.reason.clone()));
+        let event = OneBotEvent::MetaEvent {
+            time: change.timestamp,
+            self_id: bot_id,
+            meta_event_type: "bot_status".to_string(),
+            sub_type: Some(change.status.clone()),
+            status: None,
+            interval: None,
+            extra,
+        };
+        if let Err(e) = system.handle_message(&event).await {
+            eprintln!("插件系统处理机器人状态变化失败: {}", e);
+        }
+    }
+}
+
+/// 记录机器人活动；心跳事件同时更新心跳间隔
+pub async fn record_bot_activity(state: &AppState, event: &OneBotEvent) {
+    let interval = match event {
+        OneBotEvent::MetaEvent { meta_event_type, interval, .. } if meta_event_type == "heartbeat" => {
+            interval.and_then(|ms| u64::try_from(ms).ok()).filter(|ms| *ms > 0)
+        }
+        _ => None,
+    };
+
+    let mut heartbeats = state.bot_heartbeats.lock().await;
+    let heartbeat = heartbeats.entry(event.self_id()).or_insert(BotHeartbeat {
+        last_seen: tokio::time::Instant::now(),
+        interval: None,
+    });
+    heartbeat.last_seen = tokio::time::Instant::now();
+    if interval.is_some() {
+        heartbeat.interval = interval;
+    }
+}
+
+/// 开始获取机器人昵称，距上次开始不足重试间隔（仍在获取或刚失败）时返回 false
+async fn begin_nickname_lookup(state: &AppState, bot_id: i64) -> bool {
+    let mut lookups = state.nickname_lookups.lock().await;
+    let now = tokio::time::Instant::now();
+    if lookups.get(&bot_id).is_some_and(|started| now.duration_since(*started) < NICKNAME_RETRY_INTERVAL) {
+        return false;
+    }
+    lookups.insert(bot_id, now);
+    true
+}
+
+/// 定期检查心跳，超时的机器人标记为降级或离线；应用状态释放后停止
+pub fn spawn_heartbeat_watchdog(state: &Arc<AppState>) {
+    let state = Arc::downgrade(state);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            let Some(state) = state.upgrade() else { break };
+
+            let transitions: Vec<(i64, &str)> = {
+                let heartbeats = state.bot_heartbeats.lock().await;
+                heartbeats.iter()
+                    .filter_map(|(bot_id, heartbeat)| {
+                        let interval = heartbeat.interval? as f64;
+                        let elapsed = heartbeat.last_seen.elapsed().as_millis() as f64;
+                        if elapsed > interval * HEARTBEAT_OFFLINE_FACTOR {
+                            Some((*bot_id, "offline"))
+                        } else if elapsed > interval * HEARTBEAT_DEGRADED_FACTOR {
+                            Some((*bot_id, "degraded"))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            };
+
+            for (bot_id, status) in transitions {
+                let current = state.bot_accounts.lock().await.get(&bot_id).map(|account| account.status.clone());
+                // 已离线的机器人不再降级
+                if current.as_deref() == Some("offline") {
+                    continue;
+                }
+                update_bot_status(&state, bot_id, status, "心跳超时").await;
+            }
+        }
+    });
+}
+
+/// 创建 OneBot 连接断开处理函数
+pub fn bot_disconnect_handler(state: &Arc<AppState>) -> websocket_server::DisconnectCallback {
+    let state = Arc::downgrade(state);
+    Arc::new(move |bot_id| {
+        let Some(state) = state.upgrade() else { return };
+        tokio::spawn(async move {
+            // 机器人仍有其他连接时保持当前状态
+            let servers: Vec<Arc<OneBotServer>> = state.servers.lock().await.values().cloned().collect();
+            for server in servers {
+                if server.has_bot(bot_id).await {
+                    return;
+                }
+            }
+
+            state.bot_heartbeats.lock().await.remove(&bot_id);
+            update_bot_status(&state, bot_id, "offline", "连接已断开").await;
+        });
+    })
+}
+
+/// 向前端推送服务器状态变化事件
+pub async fn emit_server_status(state: &AppState, server_id: &str) {
+    let status = get_runtime_status(state, server_id).await;
+    state.emit("server-status", &status);
+}
+
+/// 按配置启动服务器实例并加入注册表
+pub async fn start_server_instance(state: &Arc<AppState>, server_config: &ServerConfig) -> Result<(), String> {
+    let server_id = server_config.id.clone();
+    server_config.validate_tls()?;
+
+    // 单个服务器的连接数上限来自全局设置
+    let mut onebot_config = server_config.to_onebot_config();
+    onebot_config.max_connections = Some(state.settings().max_connections_per_server);
+
+    let server = {
+        let mut servers = state.servers.lock().await;
+        if servers.contains_key(&server_id) {
+            return Err(format!("服务器 {} 已在运行", server_config.name));
+        }
+
+        let server = Arc::new(OneBotServer::new(onebot_config, Arc::clone(&state.logs)));
+        servers.insert(server_id.clone(), Arc::clone(&server));
+        server
+    };
+
+    // 订阅事件总线，设置回调
+    subscribe_server_events(state, &server);
+    server.set_disconnect_callback(bot_disconnect_handler(state)).await;
+    server.set_quick_operation_handler(quick_operation_handler(state)).await;
+
+    // 在后台任务中启动服务器，退出时从注册表移除
+    let (error_tx, error_rx) = tokio::sync::oneshot::channel::<String>();
+    let server_for_task = Arc::clone(&server);
+    let server_id_for_task = server_id.clone();
+    let state_for_task = Arc::downgrade(state);
+    tokio::spawn(async move {
+        let result = server_for_task.start().await;
+        let Some(state) = state_for_task.upgrade() else { return };
+
+        {
+            let mut servers = state.servers.lock().await;
+            if servers.get(&server_id_for_task).is_some_and(|s| Arc::ptr_eq(s, &server_for_task)) {
+                servers.remove(&server_id_for_task);
+            }
+        }
+
+        if let Err(e) = result {
+            eprintln!("OneBot 服务器 {} 运行失败: {}", server_id_for_task, e);
+            state.logs.add(LogEntry::new(
+                LogLevel::Error,
+                "server".to_string(),
+                format!("[ERROR] OneBot 服务器 {} 运行失败: {}", server_id_for_task, e),
+                None,
+            ));
+            let _ = error_tx.send(e.to_string());
+        }
+
+        emit_server_status(&state, &server_id_for_task).await;
+    });
+
+    // 等待一小段时间让服务器启动，期间出错（如端口被占用）直接返回
+    let result = match tokio::time::timeout(tokio::time::Duration::from_millis(500), error_rx).await {
+        Ok(Ok(error)) => Err(format!("启动服务器失败: {}", error)),
+        _ => {
+            println!("OneBot 服务器 {} 已启动: {}", server_config.name, server_config.endpoint());
+            Ok(())
+        }
+    };
+
+    emit_server_status(state, &server_id).await;
+    result
+}
+
+/// 停止服务器实例并移出注册表
+pub async fn stop_server_instance(state: &AppState, server_id: &str) -> Result<(), String> {
+    let server = state.servers.lock().await.remove(server_id);
+
+    if let Some(server) = server {
+        server.shutdown().await
+            .map_err(|e| format!("停止服务器时出错: {}", e))?;
+        println!("OneBot 服务器 {} 已停止", server_id);
+        emit_server_status(state, server_id).await;
+    }
+
+    Ok(())
+}
+
+/// 启动时自动运行标记为 auto_start 的服务器
+pub async fn auto_start_servers(state: &Arc<AppState>) {
+    let servers = {
+        let config_guard = state.config_manager.lock().await;
+        match config_guard.as_ref() {
+            Some(manager) => manager.get_servers(),
+            None => return,
+        }
+    };
+    let auto_start_enabled = state.settings().auto_start_servers;
+
+    for server in servers {
+        let should_start = auto_start_enabled && server.auto_start;
+
+        let enabled = if should_start {
+            match start_server_instance(state, &server).await {
+                Ok(_) => {
+                    state.logs.add(LogEntry::new(
+                        LogLevel::Info,
+                        "server".to_string(),
+                        format!("[INFO] 已自动启动服务器 {} ({})", server.name, server.endpoint()),
+                        None,
+                    ));
+                    true
+                }
+                Err(e) => {
+                    eprintln!("自动启动服务器 {} 失败: {}", server.name, e);
+                    state.logs.add(LogEntry::new(
+                        LogLevel::Error,
+                        "server".to_string(),
+                        format!("[ERROR] 自动启动服务器 {} ({}) 失败: {}", server.name, server.endpoint(), e),
+                        None,
+                    ));
+                    false
+                }
+            }
+        } else {
+            false
+        };
+
+        // 同步配置中的启用状态与实际运行状态
+        if server.enabled != enabled {
+            let mut config_guard = state.config_manager.lock().await;
+            if let Some(ref mut manager) = *config_guard {
+                if let Err(e) = manager.set_server_enabled(&server.id, enabled) {
+                    eprintln!("设置服务器状态失败: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 获取单个服务器的运行状态
+pub async fn get_runtime_status(state: &AppState, server_id: &str) -> ServerRuntimeStatus {
+    let server = state.servers.lock().await.get(server_id).cloned();
+
+    match server {
+        Some(server) => {
+            let connections = server.get_connections().await;
+            let status = if !connections.is_empty() {
+                "connected"
+            } else {
+                match server.get_status().await {
+                    ConnectionStatus::Connected => "listening",
+                    ConnectionStatus::Connecting => "connecting",
+                    ConnectionStatus::Disconnected => "disconnected",
+                }
+            };
+
+            // 分离式连接下同一机器人有 API 与 Event 两个连接，去重后返回
+            let mut bots: Vec<i64> = connections.iter().filter_map(|conn| conn.self_id).collect();
+            bots.sort_unstable();
+            bots.dedup();
+
+            ServerRuntimeStatus {
+                server_id: server_id.to_string(),
+                is_running: true,
+                status: status.to_string(),
+                connection_count: connections.len() as u32,
+                bots,
+                subscribers: server.event_bus().subscriber_stats(),
+                events: server.event_stats().counts(),
+            }
+        }
+        None => ServerRuntimeStatus {
+            server_id: server_id.to_string(),
+            is_running: false,
+            status: "disconnected".to_string(),
+            connection_count: 0,
+            bots: Vec::new(),
+            subscribers: Vec::new(),
+            events: event_bus::EventCounts::default(),
+        },
+    }
+}
+
+/// 启动指定的服务器
+pub async fn start_server(state: &Arc<AppState>, server_id: String) -> Result<String, String> {
+    let server_config = {
+        let config_guard = state.config_manager.lock().await;
+        let manager = config_guard.as_ref().ok_or("配置管理器未初始化")?;
+        manager.get_server(&server_id)
+            .cloned()
+            .ok_or_else(|| format!("服务器配置不存在: {}", server_id))?
+    };
+
+    start_server_instance(state, &server_config).await?;
+
+    {
+        let mut config_guard = state.config_manager.lock().await;
+        if let Some(ref mut manager) = *config_guard {
+            manager.set_server_enabled(&server_id, true)
+                .map_err(|e| format!("设置服务器状态失败: {}", e))?;
+        }
+    }
+
+    Ok(format!("OneBot 服务器已启动: {}", server_config.endpoint()))
+}
+
+/// 停止指定的服务器
+pub async fn stop_server(state: &AppState, server_id: String) -> Result<String, String> {
+    stop_server_instance(state, &server_id).await?;
+
+    {
+        let mut config_guard = state.config_manager.lock().await;
+        if let Some(ref mut manager) = *config_guard {
+            manager.set_server_enabled(&server_id, false)
+                .map_err(|e| format!("设置服务器状态失败: {}", e))?;
+        }
+    }
+
+    Ok("OneBot 服务器已停止".to_string())
+}
+
+/// 获取指定服务器的运行状态
+pub async fn get_server_status(state: &AppState, server_id: String) -> Result<ServerRuntimeStatus, String> {
+    Ok(get_runtime_status(state, &server_id).await)
+}
+
+/// 获取所有已配置服务器的运行状态
+pub async fn get_all_server_status(state: &AppState) -> Result<Vec<ServerRuntimeStatus>, String> {
+    let server_ids: Vec<String> = {
+        let config_guard = state.config_manager.lock().await;
+        let manager = config_guard.as_ref().ok_or("配置管理器未初始化")?;
+        manager.get_servers().into_iter().map(|server| server.id).collect()
+    };
+
+    let mut statuses = Vec::with_capacity(server_ids.len());
+    for server_id in server_ids {
+        statuses.push(get_runtime_status(state, &server_id).await);
+    }
+    Ok(statuses)
+}
+
+/// 载入配置管理器并加载配置目录中的发件箱，返回配置文件路径
+pub async fn initialize(state: &Arc<AppState>, manager: ConfigManager) -> String {
+    let config_path = manager.get_config_path().display().to_string();
+    let config_dir = manager.get_config_dir();
+
+    state.set_config_manager(manager).await;
+    state.init_work_dir(config_dir.clone());
+    init_outbox(state, config_dir.clone()).await;
+    init_log_storage(state, config_dir).await;
+
+    println!("配置管理器已初始化，配置文件路径: {}", config_path);
+    sync_admin_api(state).await;
+    config_path
+}
+
+/// 从配置目录加载发件箱，文件损坏时备份原文件并使用空发件箱
+async fn init_outbox(state: &AppState, config_dir: std::path::PathBuf) {
+    if state.outbox.lock().await.is_some() {
+        return;
+    }
+
+    let path = config_dir.join("outbox.json");
+    let (outbox, error) = match tokio::task::spawn_blocking(move || outbox::Outbox::load(path)).await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("加载发件箱失败: {}", e);
+            return;
+        }
+    };
+
+    if let Some(error) = error {
+        eprintln!("加载发件箱失败: {}", error);
+        state.logs.add(LogEntry::new(
+            LogLevel::Error,
+            "system".to_string(),
+            format!("[ERROR] 加载发件箱失败: {}", error),
+            None,
+        ));
+    }
+
+    let mut outbox_guard = state.outbox.lock().await;
+    if outbox_guard.is_none() {
+        *outbox_guard = Some(outbox);
+    }
+}
+
+/// 打开配置目录中的日志存储，并用最近的日志恢复日志缓冲区
+async fn init_log_storage(state: &Arc<AppState>, config_dir: std::path::PathBuf) {
+    let mut storage_guard = state.log_storage.lock().await;
+    if storage_guard.is_some() {
+        return;
+    }
+
+    match log_storage::LogStorage::open(state, config_dir.join("logs")) {
+        Ok(storage) => {
+            let limit = state.settings().max_log_entries as usize;
+            let history = storage.load_recent(limit);
+            state.logs.preload(history);
+            *storage_guard = Some(storage);
+        }
+        Err(e) => eprintln!("打开日志存储失败: {}", e),
+    }
+}
+
+/// 获取所有服务器配置
+pub async fn get_all_servers(state: &AppState) -> Result<Vec<ServerConfig>, String> {
+    let config_guard = state.config_manager.lock().await;
+    if let Some(ref manager) = *config_guard {
+        Ok(manager.get_servers())
+    } else {
+        Err("配置管理器未初始化".to_string())
+    }
+}
+
+/// 新增服务器时填写的配置项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewServerConfig {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub access_token: Option<String>,
+    pub auto_start: Option<bool>,
+    pub connection_type: Option<ConnectionMode>,
+    pub url: Option<String>,
+    pub secret: Option<String>,
+    pub post_path: Option<String>,
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+    pub ip_allowlist: Option<Vec<String>>,
+    pub ip_denylist: Option<Vec<String>>,
+}
+
+/// 添加服务器配置
+pub async fn add_server_config(state: &AppState, config: NewServerConfig) -> Result<ServerConfig, String> {
+    let NewServerConfig {
+        name,
+        host,
+        port,
+        access_token,
+        auto_start,
+        connection_type,
+        url,
+        secret,
+        post_path,
+        tls_cert_path,
+        tls_key_path,
+        ip_allowlist,
+        ip_denylist,
+    } = config;
+
+    let connection_type = connection_type.unwrap_or_default();
+    let url = url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if connection_type == ConnectionMode::ForwardWs
+        && !url.as_deref().is_some_and(|url| url.starts_with("ws://") || url.starts_with("wss://"))
+    {
+        return Err("正向 WebSocket 地址必须以 ws:// 或 wss:// 开头".to_string());
+    }
+
+    let server_id = uuid::Uuid::new_v4().to_string();
+    let mut server = ServerConfig::new(server_id, name, host, port, access_token);
+    server.auto_start = auto_start.unwrap_or(false);
+    server.connection_type = connection_type;
+    server.url = url;
+    server.secret = secret.map(|secret| secret.trim().to_string()).filter(|secret| !secret.is_empty());
+    server.post_path = post_path.map(|path| path.trim().to_string()).filter(|path| !path.is_empty());
+    if server.post_path.as_deref().is_some_and(|path| !path.starts_with('/')) {
+        return Err("HTTP POST 上报路径必须以 / 开头".to_string());
+    }
+    server.tls_cert_path = tls_cert_path.map(|path| path.trim().to_string()).filter(|path| !path.is_empty());
+    server.tls_key_path = tls_key_path.map(|path| path.trim().to_string()).filter(|path| !path.is_empty());
+    server.validate_tls()?;
+
+    // 校验 CIDR 格式，避免启动时才发现配置错误
+    let normalize_cidrs = |list: Option<Vec<String>>| -> Result<Vec<String>, String> {
+        let list: Vec<String> = list.unwrap_or_default()
+            .into_iter()
+            .map(|cidr| cidr.trim().to_string())
+            .filter(|cidr| !cidr.is_empty())
+            .collect();
+        websocket_server::parse_cidrs(&list)?;
+        Ok(list)
+    };
+    server.ip_allowlist = normalize_cidrs(ip_allowlist)?;
+    server.ip_denylist = normalize_cidrs(ip_denylist)?;
+    if connection_type == ConnectionMode::ForwardWs && !(server.ip_allowlist.is_empty() && server.ip_denylist.is_empty()) {
+        return Err("正向 WebSocket 模式不支持 IP 访问控制".to_string());
+    }
+
+    {
+        let mut config_guard = state.config_manager.lock().await;
+        if let Some(ref mut manager) = *config_guard {
+            manager.add_server(server.clone())
+                .map_err(|e| format!("添加服务器配置失败: {}", e))?;
+        } else {
+            return Err("配置管理器未初始化".to_string());
+        }
+    }
+
+    println!("已添加服务器配置: {} ({})", server.name, server.endpoint());
+    Ok(server)
+}
+
+/// 更新服务器配置
+pub async fn update_server_config(state: &AppState, server: ServerConfig) -> Result<(), String> {
+    server.validate_tls()?;
+    let mut config_guard = state.config_manager.lock().await;
+    if let Some(ref mut manager) = *config_guard {
+        manager.update_server(server)
+            .map_err(|e| format!("更新服务器配置失败: {}", e))?;
+        Ok(())
+    } else {
+        Err("配置管理器未初始化".to_string())
+    }
+}
+
+/// 删除服务器配置
+pub async fn remove_server_config(state: &AppState, server_id: String) -> Result<(), String> {
+    stop_server_instance(state, &server_id).await?;
+
+    let mut config_guard = state.config_manager.lock().await;
+    if let Some(ref mut manager) = *config_guard {
+        manager.remove_server(&server_id)
+            .map_err(|e| format!("删除服务器配置失败: {}", e))?;
+        println!("已删除服务器配置: {}", server_id);
+        Ok(())
+    } else {
+        Err("配置管理器未初始化".to_string())
+    }
+}
+
+/// 设置服务器启用状态（启用时启动监听，禁用时停止监听）
+pub async fn set_server_enabled(state: &Arc<AppState>, server_id: String, enabled: bool) -> Result<(), String> {
+    if enabled {
+        start_server(state, server_id.clone()).await?;
+    } else {
+        stop_server(state, server_id.clone()).await?;
+    }
+
+    println!("服务器 {} 状态已设置为: {}", server_id, if enabled { "启用" } else { "禁用" });
+    Ok(())
+}
+
+/// 获取配置文件路径
+pub async fn get_config_path(state: &AppState) -> Result<String, String> {
+    let config_guard = state.config_manager.lock().await;
+    if let Some(ref manager) = *config_guard {
+        Ok(manager.get_config_path().display().to_string())
+    } else {
+        Err("配置管理器未初始化".to_string())
+    }
+}
+
+/// 获取应用设置
+pub async fn get_app_settings(state: &AppState) -> Result<AppSettings, String> {
+    if state.config_manager.lock().await.is_none() {
+        return Err("配置管理器未初始化".to_string());
+    }
+    Ok(state.settings().as_ref().clone())
+}
+
+/// 更新应用设置
+pub async fn update_app_settings(state: &Arc<AppState>, settings: AppSettings) -> Result<(), String> {
+    state.update_settings(settings).await?;
+    sync_admin_api(state).await;
+    Ok(())
+}
+
+/// 按当前设置启动、重启或停止本地管理接口
+pub async fn sync_admin_api(state: &Arc<AppState>) {
+    let settings = state.settings().admin_api.clone();
+    let mut admin_api_guard = state.admin_api.lock().await;
+
+    if admin_api_guard.as_ref().is_some_and(|handle| handle.settings() == &settings) {
+        return;
+    }
+    if let Some(handle) = admin_api_guard.take() {
+        handle.stop().await;
+    }
+    if !settings.enabled {
+        return;
+    }
+
+    if settings.token.is_empty() {
+        state.logs.add(LogEntry::new(
+            LogLevel::Warning,
+            "server".to_string(),
+            "[WARN] 管理接口未设置访问令牌，已跳过启动".to_string(),
+            None,
+        ));
+        return;
+    }
+
+    match admin_api::start(state, settings.clone()) {
+        Ok(handle) => {
+            state.logs.add(LogEntry::new(
+                LogLevel::Info,
+                "server".to_string(),
+                format!("[INFO] 管理接口已启动于 {}:{}", settings.host, settings.port),
+                None,
+            ));
+            *admin_api_guard = Some(handle);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            state.logs.add(LogEntry::new(
+                LogLevel::Error,
+                "server".to_string(),
+                format!("[ERROR] {}", e),
+                None,
+            ));
+        }
+    }
+}
+
+/// 获取应用版本
+pub async fn get_app_version() -> Result<String, String> {
+    Ok(env!("CARGO_PKG_VERSION").to_string())
+}
+
+/// 获取日志历史
+pub async fn get_log_history(state: &AppState) -> Result<Vec<LogEntry>, String> {
+    Ok(state.logs.history())
+}
+
+/// 获取无法解析的 OneBot 数据
+pub async fn get_unknown_frames(state: &AppState) -> Result<Vec<LogEntry>, String> {
+    Ok(state.logs.by_category("unknown"))
+}
+
+/// 按条件查询持久化的日志
+pub async fn query_logs(state: &AppState, query: log_storage::LogQuery) -> Result<log_storage::LogQueryResult, String> {
+    let dir = {
+        let storage_guard = state.log_storage.lock().await;
+        let storage = storage_guard.as_ref().ok_or("日志存储未初始化")?;
+        storage.dir().to_path_buf()
+    };
+
+    tokio::task::spawn_blocking(move || log_storage::query(&dir, &query)).await
+        .map_err(|e| format!("查询日志失败: {}", e))
+}
+
+/// 清空日志历史：清空日志缓冲区并删除已持久化的日志分段
+pub async fn clear_log_history(state: &AppState) -> Result<(), String> {
+    state.logs.clear();
+
+    let purge = {
+        let storage_guard = state.log_storage.lock().await;
+        match storage_guard.as_ref() {
+            Some(storage) => storage.purge_task(),
+            None => return Ok(()),
+        }
+    };
+
+    tokio::task::spawn_blocking(purge).await
+        .map_err(|e| format!("清空日志存储失败: {}", e))??;
+    Ok(())
+}
+
+/// 创建 HTTP POST 快速操作处理函数，快速操作由插件系统按优先级给出
+pub fn quick_operation_handler(state: &Arc<AppState>) -> http_post::QuickOperationHandler {
+    let state = Arc::downgrade(state);
+    Arc::new(move |event| {
+        let state = state.upgrade();
+        Box::pin(async move {
+            let system = state?.plugin_system().await?;
+            match system.quick_operation(&event).await {
+                Ok(operation) => operation,
+                Err(e) => {
+                    eprintln!("插件系统生成快速操作失败: {}", e);
+                    None
+                }
+            }
+        })
+    })
+}
+
+/// 在服务器的事件总线上注册日志、账号跟踪、插件分发、前端推送与事件统计订阅者
+///
+/// 订阅者只持有应用状态的弱引用，服务器停止或应用状态释放后随事件总线一起结束。
+pub fn subscribe_server_events(state: &Arc<AppState>, server: &OneBotServer) {
+    let bus = server.event_bus();
+
+    // 日志：记录所有事件，心跳包按设置决定是否显示
+    let weak = Arc::downgrade(state);
+    bus.subscribe("logger", |_| true).spawn(move |event| {
+        let state = weak.upgrade();
+        async move {
+            let Some(state) = state else { return };
+            let is_heartbeat = matches!(&*event, OneBotEvent::MetaEvent { meta_event_type, .. } if meta_event_type == "heartbeat");
+            if is_heartbeat && !state.settings().show_heartbeat_logs {
+                return;
+            }
+
+            state.logs.add(event_log_entry(&event));
+        }
+    });
+
+    // 账号跟踪：v12 元事件可能不携带机器人账号，此时 self_id 为 0
+    let weak = Arc::downgrade(state);
+    bus.subscribe("accounts", |event| event.self_id() != 0).spawn(move |event| {
+        let state = weak.upgrade();
+        async move {
+            let Some(state) = state else { return };
+            let bot_id = event.self_id();
+
+            // 任意事件都说明机器人存活，心跳事件同时刷新心跳间隔
+            record_bot_activity(&state, &event).await;
+            update_bot_status(&state, bot_id, "online", "收到事件").await;
+
+            let needs_nickname = state.bot_accounts.lock().await
+                .get(&bot_id)
+                .is_some_and(|account| account.nickname.starts_with("Bot "));
+
+            // 如果昵称还是默认的，尝试获取真实昵称
+            if needs_nickname && begin_nickname_lookup(&state, bot_id).await {
+                tokio::spawn(async move {
+                    if let Ok(login_info) = get_bot_login_info(&state, bot_id).await {
+                        let mut accounts = state.bot_accounts.lock().await;
+                        if let Some(account) = accounts.get_mut(&bot_id) {
+                            account.nickname = login_info.nickname;
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    // 插件分发：按到达顺序逐个处理，插件处理过慢时由事件总线丢弃积压的事件并报告
+    let weak = Arc::downgrade(state);
+    bus.subscribe("plugins", |_| true).spawn(move |event| {
+        let state = weak.upgrade();
+        async move {
+            let Some(state) = state else { return };
+            let system = state.plugin_system().await;
+            if let Some(system) = system {
+                if let Err(e) = system.handle_message(&event).await {
+                    eprintln!("插件系统处理事件失败: {}", e);
+                }
+            }
+        }
+    });
+
+    // 前端聊天窗口：只关心收发的消息
+    let weak = Arc::downgrade(state);
+    bus.subscribe("ui", |event| matches!(event, OneBotEvent::Message { .. } | OneBotEvent::MessageSent { .. }))
+        .spawn(move |event| {
+            if let Some(state) = weak.upgrade() {
+                emit_chat_message(&state, &event);
+            }
+            async {}
+        });
+
+    // 事件统计：按类型计数，在运行监控中展示
+    let stats = Arc::clone(server.event_stats());
+    bus.subscribe("stats", |_| true).spawn(move |event| {
+        stats.record(&event);
+        async {}
+    });
+}
+
+/// 将 OneBot 事件转换为日志条目
+pub fn event_log_entry(event: &OneBotEvent) -> LogEntry {
+    match event {
+        OneBotEvent::Message { 
+            user_id, 
+            message_type,
+            group_id,
+            sender,
+            .. 
+        } => {
+            let sender_name = if let Some(card) = &sender.card {
+                if card.is_empty() { &sender.nickname } else { card }
+            } else {
+                &sender.nickname
+            };
+            
+            let log_content = format_event_log(event);
+            
+            LogEntry::new(
+                LogLevel::Info,
+                "message".to_string(),
+                log_content,
+                Some(serde_json::to_value(event).unwrap_or_default()),
+            ).with_message_info(
+                Some(message_type.clone()),
+                *group_id,
+                Some(*user_id),
+                Some(sender_name.to_string()),
+            )
+        }
+        OneBotEvent::MessageSent {
+            user_id,
+            target_id,
+            message_type,
+            group_id,
+            sender,
+            ..
+        } => {
+            let log_content = format_event_log(event);
+
+            LogEntry::new(
+                LogLevel::Info,
+                "message".to_string(),
+                log_content,
+                Some(serde_json::to_value(event).unwrap_or_default()),
+            ).with_message_info(
+                Some(message_type.clone()),
+                *group_id,
+                Some(target_id.unwrap_or(*user_id)),
+                Some(sender.nickname.clone()),
+            )
+        }
+        OneBotEvent::Notice { notice, .. } => {
+            let log_content = format_event_log(event);
+            LogEntry::new(
+                LogLevel::Info,
+                "notice".to_string(),
+                log_content,
+                Some(serde_json::to_value(event).unwrap_or_default()),
+            ).with_message_info(None, notice.group_id(), notice.user_id(), None)
+        }
+        OneBotEvent::Request { request, .. } => {
+            let log_content = format_event_log(event);
+            LogEntry::new(
+                LogLevel::Info,
+                "request".to_string(),
+                log_content,
+                Some(serde_json::to_value(event).unwrap_or_default()),
+            ).with_message_info(None, request.group_id(), request.user_id(), None)
+        }
+        OneBotEvent::MetaEvent { meta_event_type, .. } => {
+            let log_content = format_event_log(event);
+            let level = match meta_event_type.as_str() {
+                "heartbeat" => LogLevel::Debug,
+                _ => LogLevel::Info,
+            };
+            LogEntry::new(
+                level,
+                meta_event_type.clone(),
+                log_content,
+                Some(serde_json::to_value(event).unwrap_or_default()),
+            )
+        }
+    }
+}
+
+/// 发送消息类动作按机器人排队限速，其余动作直接放行
+pub async fn throttle_send(state: &AppState, self_id: i64, action: &str, params: &HashMap<String, serde_json::Value>) -> Result<(), String> {
+    if !send_queue::is_send_action(action) {
+        return Ok(());
+    }
+
+    let settings = state.settings();
+    let group_id = params.get("group_id").and_then(|v| v.as_i64());
+
+    state.send_limiter.acquire(self_id, group_id, &settings.rate_limit).await
+}
+
+/// 获取各机器人的发送队列统计
+pub async fn get_send_queue_stats(state: &AppState) -> Result<Vec<send_queue::SendQueueStats>, String> {
+    Ok(state.send_limiter.stats())
+}
+
+/// 查找机器人所在的服务器
+pub async fn find_bot_server(state: &AppState, self_id: i64) -> Option<Arc<OneBotServer>> {
+    let servers = state.servers.lock().await;
+    for server in servers.values() {
+        if server.has_bot(self_id).await {
+            return Some(Arc::clone(server));
+        }
+    }
+    None
+}
+
+/// 向 OneBot 客户端发送 API 请求
+///
+/// 机器人离线且启用了发件箱时，发送消息类动作会暂存到发件箱，上线后重放。
+pub async fn send_onebot_api_request(
+    state: &AppState,
+    self_id: i64,
+    action: &str,
+    params: HashMap<String, serde_json::Value>,
+) -> Result<OneBotApiResponse, String> {
+    send_onebot_api_request_with_timeout(state, self_id, action, params, None).await
+}
+
+/// 发送 OneBot API 请求并指定等待响应的超时时间
+///
+/// 超时只从请求发出时开始计算，不包括在发送队列中等待的时间；为空时按动作读取设置。
+pub async fn send_onebot_api_request_with_timeout(
+    state: &AppState,
+    self_id: i64,
+    action: &str,
+    params: HashMap<String, serde_json::Value>,
+    timeout: Option<tokio::time::Duration>,
+) -> Result<OneBotApiResponse, String> {
+    // 发件箱中还有该机器人未发送的消息时，新消息排在其后，保证发送顺序
+    if send_queue::is_send_action(action) && outbox_has_pending(state, self_id).await {
+        if let Some(id) = enqueue_outbox(state, self_id, action, params.clone(), "发件箱中还有未发送的消息").await? {
+            return Err(format!("机器人 {} 的发件箱中还有未发送的消息，本条消息已排在其后 ({})", self_id, id));
+        }
+    }
+
+    if let Some(server) = find_bot_server(state, self_id).await {
+        if !send_queue::is_send_action(action) {
+            return request_via_server(state, &server, self_id, action, params, timeout).await;
+        }
+
+        // 等待响应期间连接断开时无法确认消息是否已送达，同样加入发件箱（可能重复发送，但不会丢失）
+        match request_via_server(state, &server, self_id, action, params.clone(), timeout).await {
+            Err(e) if e == CONNECTION_CLOSED => {}
+            result => return result,
+        }
+    }
+
+    if send_queue::is_send_action(action) {
+        if let Some(id) = enqueue_outbox(state, self_id, action, params, "机器人离线或连接已断开").await? {
+            return Err(format!("机器人 {} 当前离线，消息已加入发件箱 ({})", self_id, id));
+        }
+    }
+
+    Err(format!("机器人 {} 没有活跃的 OneBot 连接", self_id))
+}
+
+/// 通过指定服务器发送 API 请求
+pub async fn request_via_server(
+    state: &AppState,
+    server: &OneBotServer,
+    self_id: i64,
+    action: &str,
+    params: HashMap<String, serde_json::Value>,
+    timeout: Option<tokio::time::Duration>,
+) -> Result<OneBotApiResponse, String> {
+    // 发送消息先经过限速队列
+    throttle_send(state, self_id, action, &params).await?;
+
+    // 构建 API 请求
+    let request = OneBotApiRequest {
+        action: action.to_string(),
+        params,
+        echo: Some(uuid::Uuid::new_v4().to_string()),
+    };
+
+    // 未指定超时时按动作读取设置，大群成员列表等请求需要更长时间
+    let timeout = timeout.unwrap_or_else(|| state.settings().api_timeout(action));
+
+    server.send_api_request(self_id, request, timeout).await
+}
+
+/// 检查启用的发件箱中是否还有该机器人未发送的消息
+async fn outbox_has_pending(state: &AppState, self_id: i64) -> bool {
+    state.settings().outbox.enabled
+        && state.outbox.lock().await.as_ref().is_some_and(|outbox| outbox.has_pending(self_id))
+}
+
+/// 将消息加入发件箱，未启用发件箱时返回 `None`
+///
+/// 加入后立即尝试重放，机器人离线时重放会直接结束，等待下次上线。
+pub async fn enqueue_outbox(
+    state: &AppState,
+    self_id: i64,
+    action: &str,
+    params: HashMap<String, serde_json::Value>,
+    reason: &str,
+) -> Result<Option<String>, String> {
+    let settings = state.settings().outbox.clone();
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let mut outbox_guard = state.outbox.lock().await;
+    let Some(outbox) = outbox_guard.as_mut() else {
+        return Ok(None);
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let message = outbox::OutboxMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        self_id,
+        action: action.to_string(),
+        params,
+        created_at: now,
+        expires_at: now + settings.expiry_secs.max(1),
+    };
+    let id = message.id.clone();
+    let snapshot = outbox.push(message);
+    drop(outbox_guard);
+    snapshot.save().await.map_err(|e| e.to_string())?;
+
+    state.logs.add(LogEntry::new(
+        LogLevel::Warning,
+        "bot".to_string(),
+        format!("[WARN] 机器人 {} 的 {} 已加入发件箱: {}", self_id, action, reason),
+        None,
+    ));
+
+    if let Some(state) = state.arc() {
+        tokio::spawn(replay_outbox(state, self_id));
+    }
+
+    Ok(Some(id))
+}
+
+/// 机器人上线后按加入顺序重放发件箱
+///
+/// 发送失败时暂停重放：机器人仍在线则等待 `OUTBOX_RETRY_DELAY` 后重试，离线后剩余消息等待下次上线。
+/// 重放期间新发送的消息同样排入发件箱（见 `send_onebot_api_request_with_timeout`），不会越过等待中的消息。
+pub async fn replay_outbox(state: Arc<AppState>, self_id: i64) {
+    {
+        let mut outbox_guard = state.outbox.lock().await;
+        let started = outbox_guard.as_mut().is_some_and(|outbox| outbox.begin_replay(self_id));
+        if !started {
+            return;
+        }
+    }
+
+    let mut state = state;
+    let mut replayed = 0;
+    loop {
+        let now = chrono::Utc::now().timestamp();
+        let message = {
+            let mut outbox_guard = state.outbox.lock().await;
+            let Some(outbox) = outbox_guard.as_mut() else { break };
+
+            let (expired, snapshot) = outbox.prune_expired(now);
+            for message in expired {
+                println!("发件箱消息 {} 已过期，不再发送", message.id);
+            }
+            // 发件箱已清空时在同一次加锁中结束重放，之后加入的消息会重新启动重放
+            let front = outbox.front(self_id);
+            if front.is_none() {
+                outbox.end_replay(self_id);
+            }
+            drop(outbox_guard);
+
+            if let Some(snapshot) = snapshot {
+                if let Err(e) = snapshot.save().await {
+                    eprintln!("清理发件箱失败: {}", e);
+                }
+            }
+
+            match front {
+                Some(message) => message,
+                None => break,
+            }
+        };
+
+        let result = match find_bot_server(&state, self_id).await {
+            Some(server) => request_via_server(&state, &server, self_id, &message.action, message.params.clone(), None).await,
+            None => Err(format!("机器人 {} 没有活跃的 OneBot 连接", self_id)),
+        };
+        match result {
+            Ok(_) => {
+                replayed += 1;
+                let snapshot = state.outbox.lock().await.as_mut().and_then(|outbox| outbox.remove(&message.id));
+                if let Some(snapshot) = snapshot {
+                    if let Err(e) = snapshot.save().await {
+                        eprintln!("更新发件箱失败: {}", e);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("重放发件箱消息 {} 失败: {}", message.id, e);
+
+                // 机器人已离线时结束重放；结束后再确认一次，避免错过期间重新上线触发的重放
+                if find_bot_server(&state, self_id).await.is_none() {
+                    let mut outbox_guard = state.outbox.lock().await;
+                    let Some(outbox) = outbox_guard.as_mut() else { break };
+                    outbox.end_replay(self_id);
+                    drop(outbox_guard);
+
+                    if find_bot_server(&state, self_id).await.is_none() {
+                        break;
+                    }
+                    let restarted = state.outbox.lock().await.as_mut().is_some_and(|outbox| outbox.begin_replay(self_id));
+                    if !restarted {
+                        break;
+                    }
+                    continue;
+                }
+
+                // 机器人仍在线，稍后重试；等待期间不持有应用状态
+                let weak = Arc::downgrade(&state);
+                drop(state);
+                tokio::time::sleep(OUTBOX_RETRY_DELAY).await;
+                let Some(upgraded) = weak.upgrade() else { return };
+                state = upgraded;
+            }
+        }
+    }
+
+    if replayed > 0 {
+        state.logs.add(LogEntry::new(
+            LogLevel::Info,
+            "bot".to_string(),
+            format!("[INFO] 机器人 {} 已重放发件箱中的 {} 条消息", self_id, replayed),
+            None,
+        ));
+    }
+}
+
+/// 获取发件箱中等待发送的消息
+pub async fn get_outbox_messages(state: &AppState) -> Result<Vec<outbox::OutboxMessage>, String> {
+    let outbox_guard = state.outbox.lock().await;
+    let outbox = outbox_guard.as_ref().ok_or("发件箱未初始化")?;
+    Ok(outbox.list(chrono::Utc::now().timestamp()))
+}
+
+/// 取消发件箱中的消息
+pub async fn cancel_outbox_message(state: &AppState, id: String) -> Result<(), String> {
+    let snapshot = {
+        let mut outbox_guard = state.outbox.lock().await;
+        let outbox = outbox_guard.as_mut().ok_or("发件箱未初始化")?;
+        outbox.remove(&id).ok_or_else(|| format!("发件箱中不存在消息 {}", id))?
+    };
+    snapshot.save().await.map_err(|e| e.to_string())
+}
+
+/// 获取好友列表（带缓存）
+///
+/// 调用 API 期间不持有账号缓存的锁，避免阻塞心跳、状态更新等其他访问。
+pub async fn get_friend_list_cached(state: &AppState, self_id: i64) -> Result<Vec<Friend>, String> {
+    let current_time = chrono::Utc::now().timestamp();
+
+    // 检查缓存是否有效
+    if let Some(account) = state.bot_accounts.lock().await.get(&self_id) {
+        if current_time - account.friends_updated < CACHE_DURATION {
+            return Ok(account.friends.clone());
+        }
+    }
+
+    // 调用真实的 OneBot API
+    let params = HashMap::new();
+    let response = send_onebot_api_request(state, self_id, "get_friend_list", params).await?;
+
+    // 解析响应
+    if response.status == "ok" && response.retcode == 0 {
+        if let Some(data) = response.data {
+            let friends: Vec<Friend> = serde_json::from_value(data)
+                .map_err(|e| format!("解析好友列表失败: {}", e))?;
+
+            // 更新缓存
+            let mut accounts = state.bot_accounts.lock().await;
+            let account = accounts.entry(self_id).or_insert_with(|| new_bot_account(self_id));
+            account.friends = friends.clone();
+            account.friends_updated = current_time;
+
+            return Ok(friends);
+        }
+    }
+
+    Err(format!("API 调用失败: {} ({})",
+        response.message.unwrap_or_default(),
+        response.retcode))
+}
+
+/// 获取群聊列表（带缓存）
+///
+/// 调用 API 期间不持有账号缓存的锁，避免阻塞心跳、状态更新等其他访问。
+pub async fn get_group_list_cached(state: &AppState, self_id: i64) -> Result<Vec<Group>, String> {
+    let current_time = chrono::Utc::now().timestamp();
+
+    // 检查缓存是否有效
+    if let Some(account) = state.bot_accounts.lock().await.get(&self_id) {
+        if current_time - account.groups_updated < CACHE_DURATION {
+            return Ok(account.groups.clone());
+        }
+    }
+
+    // 调用真实的 OneBot API
+    let params = HashMap::new();
+    let response = send_onebot_api_request(state, self_id, "get_group_list", params).await?;
+
+    // 解析响应
+    if response.status == "ok" && response.retcode == 0 {
+        if let Some(data) = response.data {
+            let groups: Vec<Group> = serde_json::from_value(data)
+                .map_err(|e| format!("解析群聊列表失败: {}", e))?;
+
+            // 更新缓存
+            let mut accounts = state.bot_accounts.lock().await;
+            let account = accounts.entry(self_id).or_insert_with(|| new_bot_account(self_id));
+            account.groups = groups.clone();
+            account.groups_updated = current_time;
+
+            return Ok(groups);
+        }
+    }
+
+    Err(format!("API 调用失败: {} ({})",
+        response.message.unwrap_or_default(),
+        response.retcode))
+}
+
+/// 调用 API 时账号缓存中还没有该机器人，按在线账号创建缓存项
+fn new_bot_account(self_id: i64) -> BotAccount {
+    BotAccount {
+        self_id,
+        nickname: format!("Bot {}", self_id),
+        status: "online".to_string(),
+        friends: Vec::new(),
+        groups: Vec::new(),
+        friends_updated: 0,
+        groups_updated: 0,
+    }
+}
+
+/// 获取机器人登录信息
+pub async fn get_bot_login_info(state: &AppState, self_id: i64) -> Result<BotLoginInfo, String> {
+    let params = HashMap::new();
+    let response = send_onebot_api_request(state, self_id, "get_login_info", params).await?;
+
+    if response.status == "ok" && response.retcode == 0 {
+        if let Some(data) = response.data {
+            let login_info: BotLoginInfo = serde_json::from_value(data)
+                .map_err(|e| format!("解析登录信息失败: {}", e))?;
+            return Ok(login_info);
+        }
+    }
+
+    Err(format!("获取登录信息失败: {} ({})",
+        response.message.unwrap_or_default(),
+        response.retcode))
+}
+
+/// 获取所有机器人账号
+pub async fn get_bot_accounts(state: &AppState) -> Result<Vec<BotAccount>, String> {
+    let accounts = state.bot_accounts.lock().await;
+    Ok(accounts.values().cloned().collect())
+}
+
+/// 获取指定机器人的好友列表
+pub async fn get_friends(state: &AppState, self_id: i64) -> Result<Vec<Friend>, String> {
+    get_friend_list_cached(state, self_id).await
+}
+
+/// 获取指定机器人的群聊列表
+pub async fn get_groups(state: &AppState, self_id: i64) -> Result<Vec<Group>, String> {
+    get_group_list_cached(state, self_id).await
+}
+
+/// 刷新机器人数据（清除缓存）
+pub async fn refresh_bot_data(state: &AppState, self_id: Option<i64>) -> Result<(), String> {
+    let mut accounts = state.bot_accounts.lock().await;
+
+    if let Some(id) = self_id {
+        // 刷新指定机器人的数据
+        if let Some(account) = accounts.get_mut(&id) {
+            // 强制过期缓存
+            account.friends_updated = 0;
+            account.groups_updated = 0;
+        }
+    } else {
+        // 刷新所有机器人的数据
+        for account in accounts.values_mut() {
+            account.friends_updated = 0;
+            account.groups_updated = 0;
+        }
+    }
+
+    Ok(())
+}
+
+/// 确定发送消息使用的机器人账号
+///
+/// 未指定时使用任意一个已连接的机器人
+pub async fn resolve_bot_id(state: &AppState, self_id: Option<i64>) -> Result<i64, String> {
+    if let Some(id) = self_id {
+        return Ok(id);
+    }
+
+    let servers = state.servers.lock().await;
+    for server in servers.values() {
+        if let Some(id) = server.get_connections().await.into_iter().find_map(|conn| conn.self_id) {
+            return Ok(id);
+        }
+    }
+
+    Err("没有活跃的 OneBot 连接".to_string())
+}
+
+/// 发送私聊消息
+pub async fn send_private_message(
+    state: &AppState,
+    user_id: i64,
+    message: String,
+    self_id: Option<i64>,
+) -> Result<SendMessageResponse, String> {
+    let self_id = resolve_bot_id(state, self_id).await?;
+
+    let mut params = HashMap::new();
+    params.insert("user_id".to_string(), serde_json::Value::Number(serde_json::Number::from(user_id)));
+    params.insert("message".to_string(), serde_json::Value::String(message));
+
+    let response = send_onebot_api_request(state, self_id, "send_private_msg", params).await?;
+
+    if response.status == "ok" && response.retcode == 0 {
+        if let Some(data) = response.data {
+            let send_response: SendMessageResponse = serde_json::from_value(data)
+                .map_err(|e| format!("解析发送响应失败: {}", e))?;
+            return Ok(send_response);
+        }
+    }
+
+    Err(format!("发送私聊消息失败: {} ({})",
+        response.message.unwrap_or_default(),
+        response.retcode))
+}
+
+/// 发送群聊消息
+pub async fn send_group_message(
+    state: &AppState,
+    group_id: i64,
+    message: String,
+    self_id: Option<i64>,
+) -> Result<SendMessageResponse, String> {
+    let self_id = resolve_bot_id(state, self_id).await?;
+
+    let mut params = HashMap::new();
+    params.insert("group_id".to_string(), serde_json::Value::Number(serde_json::Number::from(group_id)));
+    params.insert("message".to_string(), serde_json::Value::String(message));
+
+    let response = send_onebot_api_request(state, self_id, "send_group_msg", params).await?;
+
+    if response.status == "ok" && response.retcode == 0 {
+        if let Some(data) = response.data {
+            let send_response: SendMessageResponse = serde_json::from_value(data)
+                .map_err(|e| format!("解析发送响应失败: {}", e))?;
+            return Ok(send_response);
+        }
+    }
+
+    Err(format!("发送群聊消息失败: {} ({})",
+        response.message.unwrap_or_default(),
+        response.retcode))
+}
+
+/// 初始化插件系统
+pub async fn init_plugin_system(state: &Arc<AppState>) -> Result<String, String> {
+    // 初始化插件系统，插件通过应用状态调用 OneBot API
+    let work_dir = state.work_dir()?;
+    let plugin_system = plugins::init_plugin_system(Arc::downgrade(state), work_dir).await
+        .map_err(|e| format!("初始化插件系统失败: {}", e))?;
+
+    // 保存到应用状态
+    {
+        let mut system_guard = state.plugin_system.write().await;
+        *system_guard = Some(plugin_system);
+    }
+
+    Ok("插件系统初始化成功".to_string())
+}
+
+/// 停止管理接口与所有服务器并卸载插件，退出前调用
+pub async fn shutdown(state: &AppState) {
+    if let Some(handle) = state.admin_api.lock().await.take() {
+        handle.stop().await;
+    }
+
+    let server_ids: Vec<String> = state.servers.lock().await.keys().cloned().collect();
+    for server_id in server_ids {
+        if let Err(e) = stop_server_instance(state, &server_id).await {
+            eprintln!("停止服务器 {} 失败: {}", server_id, e);
+        }
+    }
+
+    if let Some(system) = state.plugin_system().await {
+        system.manager.write().await.unload_all_plugins().await;
+    }
+}
+
+/// 关闭日志订阅并等待日志写入磁盘，退出前最后调用
+pub async fn close_logs(state: &AppState) {
+    state.logs.close_subscribers();
+
+    let storage = state.log_storage.lock().await.take();
+    if let Some(storage) = storage {
+        let _ = tokio::task::spawn_blocking(move || storage.close()).await;
+    }
+}
+
+/// 获取插件配置
+pub async fn get_plugin_config(state: &AppState, plugin_name: String) -> Result<plugins::config::PluginConfig, String> {
+    let plugins_dir = plugins::config::plugins_dir(&state.work_dir()?);
+    plugins::config::PluginConfig::load_for_plugin(&plugins_dir, &plugin_name).await
+        .map_err(|e| format!("获取插件配置失败: {}", e))
+}
+
+/// 更新插件配置
+pub async fn update_plugin_config(state: &AppState, config: plugins::config::PluginConfig) -> Result<(), String> {
+    let plugins_dir = plugins::config::plugins_dir(&state.work_dir()?);
+    config.save(&plugins_dir).await
+        .map_err(|e| format!("保存插件配置失败: {}", e))
+}
+
+/// 获取全局插件配置
+pub async fn get_global_plugin_config(state: &AppState) -> Result<plugins::config::GlobalPluginConfig, String> {
+    plugins::config::GlobalPluginConfig::load_or_default(&state.work_dir()?).await
+        .map_err(|e| format!("获取全局插件配置失败: {}", e))
+}
+
+/// 更新全局插件配置
+pub async fn update_global_plugin_config(state: &AppState, config: plugins::config::GlobalPluginConfig) -> Result<(), String> {
+    config.save(&state.work_dir()?).await
+        .map_err(|e| format!("保存全局插件配置失败: {}", e))
+}
+
+/// 获取所有插件
+pub async fn get_all_plugins(state: &AppState) -> Result<Vec<plugins::PluginMetadata>, String> {
+    let system_guard = state.plugin_system.read().await;
+    if let Some(ref system) = *system_guard {
+        let manager = system.manager.read().await;
+        Ok(manager.get_all_plugins())
+    } else {
+        Err("插件系统未初始化".to_string())
+    }
+}
+
+/// 启用插件
+pub async fn enable_plugin(state: &AppState, plugin_id: String) -> Result<(), String> {
+    let system_guard = state.plugin_system.read().await;
+    if let Some(ref system) = *system_guard {
+        let mut manager = system.manager.write().await;
+        let uuid = uuid::Uuid::parse_str(&plugin_id)
+            .map_err(|e| format!("无效的插件ID: {}", e))?;
+        manager.enable_plugin(&uuid).await
+            .map_err(|e| format!("启用插件失败: {}", e))
+    } else {
+        Err("插件系统未初始化".to_string())
+    }
+}
+
+/// 禁用插件
+pub async fn disable_plugin(state: &AppState, plugin_id: String) -> Result<(), String> {
+    let system_guard = state.plugin_system.read().await;
+    if let Some(ref system) = *system_guard {
+        let mut manager = system.manager.write().await;
+        let uuid = uuid::Uuid::parse_str(&plugin_id)
+            .map_err(|e| format!("无效的插件ID: {}", e))?;
+        manager.disable_plugin(&uuid).await
+            .map_err(|e| format!("禁用插件失败: {}", e))
+    } else {
+        Err("插件系统未初始化".to_string())
+    }
+}
+
+/// 卸载插件
+pub async fn unload_plugin(state: &AppState, plugin_id: String) -> Result<(), String> {
+    let system_guard = state.plugin_system.read().await;
+    if let Some(ref system) = *system_guard {
+        let mut manager = system.manager.write().await;
+        let uuid = uuid::Uuid::parse_str(&plugin_id)
+            .map_err(|e| format!("无效的插件ID: {}", e))?;
+        manager.unload_plugin(&uuid).await
+            .map_err(|e| format!("卸载插件失败: {}", e))
+    } else {
+        Err("插件系统未初始化".to_string())
+    }
+}
+
+/// 获取插件统计信息
+pub async fn get_plugin_stats(state: &AppState, plugin_id: String) -> Result<plugins::PluginStats, String> {
+    let system_guard = state.plugin_system.read().await;
+    if let Some(ref system) = *system_guard {
+        let manager = system.manager.read().await;
+        let uuid = uuid::Uuid::parse_str(&plugin_id)
+            .map_err(|e| format!("无效的插件ID: {}", e))?;
+        manager.get_plugin_stats(&uuid)
+            .cloned()
+            .ok_or_else(|| "插件不存在".to_string())
+    } else {
+        Err("插件系统未初始化".to_string())
+    }
+}
+
+/// 服务器状态信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerStatusInfo {
+    pub is_running: bool,
+    pub status: String,
+    pub connection_count: u32,
+    pub active_bots: Vec<i64>,
+}
+
+/// 获取详细的服务器状态信息（汇总所有运行中的服务器）
+pub async fn get_server_status_info(state: &AppState) -> Result<ServerStatusInfo, String> {
+    let server_ids: Vec<String> = state.servers.lock().await.keys().cloned().collect();
+
+    let mut connection_count = 0;
+    for server_id in &server_ids {
+        connection_count += get_runtime_status(state, server_id).await.connection_count;
+    }
+
+    let status = if connection_count > 0 {
+        "connected"
+    } else if !server_ids.is_empty() {
+        "listening"
+    } else {
+        "disconnected"
+    };
+
+    let accounts = state.bot_accounts.lock().await;
+    let active_bots: Vec<i64> = accounts.keys().cloned().collect();
+
+    Ok(ServerStatusInfo {
+        is_running: !server_ids.is_empty(),
+        status: status.to_string(),
+        connection_count,
+        active_bots,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    #[tokio::test]
+    async fn send_is_queued_when_connection_closes_mid_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+        initialize(&state, ConfigManager::from_dir(dir.path().to_path_buf()).unwrap()).await;
+
+        let mut settings = get_app_settings(&state).await.unwrap();
+        settings.outbox.enabled = true;
+        settings.rate_limit.enabled = false;
+        update_app_settings(&state, settings).await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server_config = ServerConfig::new("ws".to_string(), "ws".to_string(), "127.0.0.1".to_string(), port, None);
+        start_server_instance(&state, &server_config).await.unwrap();
+
+        let mut request = format!("ws://127.0.0.1:{}/", port).into_client_request().unwrap();
+        request.headers_mut().insert("X-Self-ID", "10001".parse().unwrap());
+        let (mut client, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        for _ in 0..100 {
+            if find_bot_server(&state, 10001).await.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // 机器人收到请求后不响应，直接断开连接
+        let bot = tokio::spawn(async move {
+            let _ = client.next().await;
+        });
+
+        let params = HashMap::from([
+            ("user_id".to_string(), serde_json::json!(30003)),
+            ("message".to_string(), serde_json::json!("hi")),
+        ]);
+        let error = send_onebot_api_request(&state, 10001, "send_private_msg", params).await.unwrap_err();
+        assert!(error.contains("已加入发件箱"), "{}", error);
+        bot.await.unwrap();
+
+        let messages = get_outbox_messages(&state).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].action, "send_private_msg");
+        assert!(dir.path().join("outbox.json").exists());
+
+        shutdown(&state).await;
+    }
+
+    #[tokio::test]
+    async fn outbox_retries_while_online_and_keeps_new_sends_in_order() {
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+        initialize(&state, ConfigManager::from_dir(dir.path().to_path_buf()).unwrap()).await;
+
+        let mut settings = get_app_settings(&state).await.unwrap();
+        settings.outbox.enabled = true;
+        settings.api_timeout_secs = 1;
+        update_app_settings(&state, settings).await.unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server_config = ServerConfig::new("ws".to_string(), "ws".to_string(), "127.0.0.1".to_string(), port, None);
+        start_server_instance(&state, &server_config).await.unwrap();
+
+        let mut request = format!("ws://127.0.0.1:{}/", port).into_client_request().unwrap();
+        request.headers_mut().insert("X-Self-ID", "10001".parse().unwrap());
+        let (mut client, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        for _ in 0..100 {
+            if find_bot_server(&state, 10001).await.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // 机器人不响应收到的第一个请求，之后的请求正常响应，返回收到的消息内容
+        let bot = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while received.len() < 3 {
+                let Some(Ok(Message::Text(text))) = client.next().await else { break };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                received.push(request["params"]["message"].as_str().unwrap().to_string());
+                if received.len() > 1 {
+                    let response = serde_json::json!({ "status": "ok", "retcode": 0, "data": null, "echo": request["echo"] });
+                    client.send(Message::Text(response.to_string())).await.unwrap();
+                }
+            }
+            received
+        });
+
+        let message = |text: &str| HashMap::from([
+            ("user_id".to_string(), serde_json::json!(30003)),
+            ("message".to_string(), serde_json::json!(text)),
+        ]);
+        enqueue_outbox(&state, 10001, "send_private_msg", message("first"), "测试").await.unwrap().unwrap();
+
+        // 首次重放超时后发件箱仍有消息，新消息排在其后而不是直接发送
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        let error = send_onebot_api_request(&state, 10001, "send_private_msg", message("second")).await.unwrap_err();
+        assert!(error.contains("已排在其后"), "{}", error);
+        assert_eq!(get_outbox_messages(&state).await.unwrap().len(), 2);
+
+        let received = tokio::time::timeout(std::time::Duration::from_secs(10), bot).await.unwrap().unwrap();
+        assert_eq!(received, ["first", "first", "second"]);
+        for _ in 0..100 {
+            if get_outbox_messages(&state).await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(get_outbox_messages(&state).await.unwrap().is_empty());
+
+        shutdown(&state).await;
+    }
+
+    #[tokio::test]
+    async fn friend_and_group_caches_expire_separately_without_holding_accounts_lock() {
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+        initialize(&state, ConfigManager::from_dir(dir.path().to_path_buf()).unwrap()).await;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server_config = ServerConfig::new("ws".to_string(), "ws".to_string(), "127.0.0.1".to_string(), port, None);
+        start_server_instance(&state, &server_config).await.unwrap();
+
+        let mut request = format!("ws://127.0.0.1:{}/", port).into_client_request().unwrap();
+        request.headers_mut().insert("X-Self-ID", "10001".parse().unwrap());
+        let (mut client, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        for _ in 0..100 {
+            if find_bot_server(&state, 10001).await.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+
+        // 模拟的机器人在收到 release 信号后才响应，返回收到的动作列表
+        let (release_tx, mut release_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+        let (action_tx, mut action_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let bot = tokio::spawn(async move {
+            while let Some(Ok(Message::Text(text))) = client.next().await {
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                let action = request["action"].as_str().unwrap().to_string();
+                let data = match action.as_str() {
+                    "get_friend_list" => serde_json::json!([{ "user_id": 30003, "nickname": "friend", "remark": "" }]),
+                    "get_group_list" => serde_json::json!([{ "group_id": 20002, "group_name": "group", "member_count": 1, "max_member_count": 200 }]),
+                    _ => continue,
+                };
+                action_tx.send(action).unwrap();
+                if release_rx.recv().await.is_none() {
+                    break;
+                }
+                let response = serde_json::json!({ "status": "ok", "retcode": 0, "data": data, "echo": request["echo"] });
+                client.send(Message::Text(response.to_string())).await.unwrap();
+            }
+        });
+
+        let friends = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move { get_friend_list_cached(&state, 10001).await }
+        });
+        assert_eq!(action_rx.recv().await.unwrap(), "get_friend_list");
+        // 等待响应期间账号缓存没有被锁住
+        assert!(state.bot_accounts.try_lock().is_ok());
+        release_tx.send(()).unwrap();
+        assert_eq!(friends.await.unwrap().unwrap().len(), 1);
+
+        // 好友列表刚更新不代表群聊列表有效，仍需请求
+        let groups = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move { get_group_list_cached(&state, 10001).await }
+        });
+        assert_eq!(action_rx.recv().await.unwrap(), "get_group_list");
+        release_tx.send(()).unwrap();
+        assert_eq!(groups.await.unwrap().unwrap()[0].group_id, 20002);
+
+        // 两份缓存都有效时不再请求
+        assert_eq!(get_friend_list_cached(&state, 10001).await.unwrap()[0].user_id, 30003);
+        assert_eq!(get_group_list_cached(&state, 10001).await.unwrap().len(), 1);
+        assert!(action_rx.try_recv().is_err());
+
+        drop(release_tx);
+        shutdown(&state).await;
+        let _ = bot.await;
+    }
+
+    fn heartbeat(bot_id: i64, interval: i64) -> OneBotEvent {
+        OneBotEvent::MetaEvent {
+            time: 0,
+            self_id: bot_id,
+            meta_event_type: "heartbeat".to_string(),
+            sub_type: None,
+            status: None,
+            interval: Some(interval),
+            extra: HashMap::new(),
+        }
+    }
+
+    async fn bot_status(state: &AppState, bot_id: i64) -> String {
+        state.bot_accounts.lock().await[&bot_id].status.clone()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missed_heartbeats_degrade_then_take_bot_offline() {
+        use tokio::time::{sleep, Duration};
+
+        let state = AppState::new();
+        spawn_heartbeat_watchdog(&state);
+
+        // 与账号跟踪订阅者一致：收到事件后记录活动并标记在线
+        let receive_heartbeat = |state: Arc<AppState>| async move {
+            record_bot_activity(&state, &heartbeat(10001, 1000)).await;
+            update_bot_status(&state, 10001, "online", "收到事件").await;
+        };
+        receive_heartbeat(Arc::clone(&state)).await;
+
+        sleep(Duration::from_millis(1100)).await;
+        assert_eq!(bot_status(&state, 10001).await, "online");
+
+        // 超过 1.5 倍间隔降级，超过 3 倍间隔离线
+        sleep(Duration::from_millis(1000)).await;
+        assert_eq!(bot_status(&state, 10001).await, "degraded");
+        sleep(Duration::from_millis(1000)).await;
+        assert_eq!(bot_status(&state, 10001).await, "degraded");
+        sleep(Duration::from_millis(1000)).await;
+        assert_eq!(bot_status(&state, 10001).await, "offline");
+
+        // 离线后不会再被降级，收到心跳后恢复在线
+        sleep(Duration::from_millis(3000)).await;
+        assert_eq!(bot_status(&state, 10001).await, "offline");
+        receive_heartbeat(Arc::clone(&state)).await;
+        sleep(Duration::from_millis(1100)).await;
+        assert_eq!(bot_status(&state, 10001).await, "online");
+
+        let transitions: Vec<String> = state.logs.history().into_iter()
+            .filter(|entry| entry.category == "bot")
+            .map(|entry| entry.content)
+            .collect();
+        assert_eq!(transitions.len(), 4, "{:?}", transitions);
+        assert!(transitions[1].contains("online -> degraded"));
+        assert!(transitions[2].contains("degraded -> offline"));
+        assert!(transitions[3].contains("offline -> online"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nickname_lookup_is_not_repeated_within_retry_interval() {
+        let state = AppState::new();
+        assert!(begin_nickname_lookup(&state, 10001).await);
+        assert!(!begin_nickname_lookup(&state, 10001).await);
+        assert!(begin_nickname_lookup(&state, 10002).await);
+
+        tokio::time::sleep(NICKNAME_RETRY_INTERVAL).await;
+        assert!(begin_nickname_lookup(&state, 10001).await);
+    }
+
+    #[tokio::test]
+    async fn tls_paths_are_rejected_outside_reverse_websocket() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+        initialize(&state, ConfigManager::from_dir(dir.path().to_path_buf()).unwrap()).await;
+
+        for (connection_type, url) in [(ConnectionMode::HttpPost, None), (ConnectionMode::ForwardWs, Some("wss://example.com/"))] {
+            let error = add_server_config(&state, NewServerConfig {
+                name: "tls".to_string(),
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                connection_type: Some(connection_type),
+                url: url.map(str::to_string),
+                tls_cert_path: Some("cert.pem".to_string()),
+                tls_key_path: Some("key.pem".to_string()),
+                ..Default::default()
+            }).await.unwrap_err();
+            assert!(error.contains("只有反向 WebSocket"), "{}", error);
+        }
+        assert!(get_all_servers(&state).await.unwrap().is_empty());
+
+        // 已保存的配置改为非反向模式时同样拒绝，启动时也会再次校验
+        let mut server = add_server_config(&state, NewServerConfig {
+            name: "tls".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            tls_cert_path: Some("cert.pem".to_string()),
+            tls_key_path: Some("key.pem".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        server.connection_type = ConnectionMode::HttpPost;
+        assert!(update_server_config(&state, server.clone()).await.is_err());
+        assert!(start_server_instance(&state, &server).await.is_err());
+        assert!(state.servers.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn clear_log_history_removes_persisted_logs() {
+        async fn wait_for_total(state: &AppState, total: usize) {
+            for _ in 0..100 {
+                if query_logs(state, log_storage::LogQuery::default()).await.unwrap().entries.len() == total {
+                    return;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            panic!("持久化的日志条数没有达到 {}", total);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+        initialize(&state, ConfigManager::from_dir(dir.path().to_path_buf()).unwrap()).await;
+
+        for content in ["a", "b"] {
+            state.logs.add(LogEntry::new(LogLevel::Info, "test".to_string(), content.to_string(), None));
+        }
+        wait_for_total(&state, 2).await;
+
+        clear_log_history(&state).await.unwrap();
+        assert!(state.logs.history().is_empty());
+        assert!(query_logs(&state, log_storage::LogQuery::default()).await.unwrap().entries.is_empty());
+
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        state.logs.add(LogEntry::new(LogLevel::Info, "test".to_string(), "c".to_string(), None));
+        wait_for_total(&state, 1).await;
+
+        shutdown(&state).await;
+        close_logs(&state).await;
+        let storage = log_storage::LogStorage::open(&AppState::new(), dir.path().join("logs")).unwrap();
+        let recent = storage.load_recent(10);
+        assert_eq!(recent.iter().map(|entry| entry.content.as_str()).collect::<Vec<_>>(), ["c"]);
+    }
+
+    #[tokio::test]
+    async fn app_states_run_side_by_side_in_separate_dirs() {
+        async fn run(dir: std::path::PathBuf, prefix: &str) -> (Arc<AppState>, u16) {
+            let state = AppState::new();
+            initialize(&state, ConfigManager::from_dir(dir).unwrap()).await;
+            init_plugin_system(&state).await.unwrap();
+
+            let mut config = get_global_plugin_config(&state).await.unwrap();
+            config.command_prefix = prefix.to_string();
+            update_global_plugin_config(&state, config).await.unwrap();
+
+            let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let server_config = ServerConfig::new("ws".to_string(), "ws".to_string(), "127.0.0.1".to_string(), port, None);
+            start_server_instance(&state, &server_config).await.unwrap();
+            (state, port)
+        }
+
+        let dir_a = tempfile::tempdir().unwrap();
+        let dir_b = tempfile::tempdir().unwrap();
+        let ((state_a, port_a), (state_b, port_b)) = tokio::join!(
+            run(dir_a.path().to_path_buf(), "!"),
+            run(dir_b.path().to_path_buf(), "#"),
+        );
+        assert_ne!(port_a, port_b);
+
+        for (state, dir, port, prefix) in [(&state_a, &dir_a, port_a, "!"), (&state_b, &dir_b, port_b, "#")] {
+            assert!(dir.path().join("plugins").is_dir());
+            assert!(dir.path().join("logs").is_dir());
+            assert_eq!(get_global_plugin_config(state).await.unwrap().command_prefix, prefix);
+
+            let mut request = format!("ws://127.0.0.1:{}/", port).into_client_request().unwrap();
+            request.headers_mut().insert("X-Self-ID", "10001".parse().unwrap());
+            tokio_tungstenite::connect_async(request).await.unwrap();
+        }
+
+        shutdown(&state_a).await;
+        shutdown(&state_b).await;
+    }
+}
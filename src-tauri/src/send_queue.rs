@@ -0,0 +1,292 @@
+use crate::config::RateLimitSettings;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// 需要经过发送队列限速的动作
+const SEND_ACTIONS: &[&str] = &[
+    "send_msg",
+    "send_private_msg",
+    "send_group_msg",
+    "send_private_forward_msg",
+    "send_group_forward_msg",
+];
+
+/// 判断动作是否为发送消息
+pub fn is_send_action(action: &str) -> bool {
+    SEND_ACTIONS.contains(&action)
+}
+
+/// 发送队列统计信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendQueueStats {
+    pub self_id: i64,
+    pub queue_depth: usize, // 当前排队中的发送请求数
+    pub sent: u64,          // 已放行的发送请求数
+    pub delayed: u64,       // 因限速而等待过的发送请求数
+    pub dropped: u64,       // 因队列已满被丢弃的发送请求数
+}
+
+/// 令牌桶
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: u32) -> Self {
+        Self {
+            tokens: burst as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 按速率补充令牌，返回取得一个令牌还需等待的时间
+    fn wait_time(&mut self, rate: f64, burst: u32, now: Instant) -> Duration {
+        let capacity = burst.max(1) as f64;
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 || rate <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / rate)
+        }
+    }
+
+    /// 等待到至少有一个令牌可用，返回是否等待过
+    async fn wait(&mut self, rate: f64, burst: u32) -> bool {
+        let mut delayed = false;
+        loop {
+            let wait = self.wait_time(rate, burst, Instant::now());
+            if wait.is_zero() {
+                return delayed;
+            }
+            delayed = true;
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn consume(&mut self) {
+        self.tokens = (self.tokens - 1.0).max(0.0);
+    }
+}
+
+/// 单个机器人的发送队列
+struct BotQueue {
+    /// 全局令牌桶，持有锁的请求位于机器人队首
+    global: Mutex<Option<TokenBucket>>,
+    /// 按群划分的令牌桶，持有锁的请求位于该群队首
+    groups: std::sync::Mutex<HashMap<i64, Arc<Mutex<TokenBucket>>>>,
+    depth: AtomicUsize,
+    sent: AtomicU64,
+    delayed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl BotQueue {
+    fn group(&self, group_id: i64, burst: u32) -> Arc<Mutex<TokenBucket>> {
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(groups.entry(group_id).or_insert_with(|| Arc::new(Mutex::new(TokenBucket::new(burst)))))
+    }
+}
+
+/// 排队计数守卫，请求被取消时同样减少队列深度
+struct DepthGuard<'a>(&'a AtomicUsize);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 按机器人划分的发送限速器
+///
+/// 发往群的请求先在该群的队列中等待群令牌，再与机器人的其他请求按顺序等待全局令牌，
+/// 等待群令牌时不占用机器人队首，某个群被限速不会阻塞发往其他群或私聊的消息。
+/// 放行前再随机等待一段抖动时间，避免发送间隔过于规律；抖动在释放令牌桶后等待，不阻塞后续请求取令牌。
+#[derive(Default)]
+pub struct SendLimiter {
+    queues: std::sync::Mutex<HashMap<i64, Arc<BotQueue>>>,
+}
+
+impl SendLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn queue(&self, self_id: i64) -> Arc<BotQueue> {
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(queues.entry(self_id).or_insert_with(|| Arc::new(BotQueue {
+            global: Mutex::new(None),
+            groups: std::sync::Mutex::new(HashMap::new()),
+            depth: AtomicUsize::new(0),
+            sent: AtomicU64::new(0),
+            delayed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        })))
+    }
+
+    /// 等待发送许可；队列已满时返回错误
+    pub async fn acquire(&self, self_id: i64, group_id: Option<i64>, settings: &RateLimitSettings) -> Result<(), String> {
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        let queue = self.queue(self_id);
+        if queue.depth.fetch_add(1, Ordering::SeqCst) >= settings.max_queue_depth.max(1) {
+            queue.depth.fetch_sub(1, Ordering::SeqCst);
+            queue.dropped.fetch_add(1, Ordering::Relaxed);
+            return Err(format!("机器人 {} 的发送队列已满，消息已丢弃", self_id));
+        }
+        let _depth = DepthGuard(&queue.depth);
+
+        let mut delayed = false;
+
+        // 先等待群令牌，期间只占用该群的队首；令牌只会随时间增加，拿到全局令牌前不会失效
+        let group = group_id.map(|group_id| queue.group(group_id, settings.group_burst));
+        let mut group_bucket = match group {
+            Some(ref group) => Some(group.lock().await),
+            None => None,
+        };
+        if let Some(ref mut bucket) = group_bucket {
+            delayed |= bucket.wait(settings.group_rate, settings.group_burst).await;
+        }
+
+        {
+            let mut global = queue.global.lock().await;
+            let global = global.get_or_insert_with(|| TokenBucket::new(settings.global_burst));
+            delayed |= global.wait(settings.global_rate, settings.global_burst).await;
+            global.consume();
+            if let Some(ref mut bucket) = group_bucket {
+                bucket.consume();
+            }
+            drop(group_bucket);
+        }
+
+        if settings.jitter_ms > 0 {
+            let jitter = rand::thread_rng().gen_range(0..=settings.jitter_ms);
+            tokio::time::sleep(Duration::from_millis(jitter)).await;
+        }
+
+        if delayed {
+            queue.delayed.fetch_add(1, Ordering::Relaxed);
+        }
+        queue.sent.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 获取所有机器人的发送队列统计
+    pub fn stats(&self) -> Vec<SendQueueStats> {
+        let queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stats: Vec<SendQueueStats> = queues.iter()
+            .map(|(self_id, queue)| SendQueueStats {
+                self_id: *self_id,
+                queue_depth: queue.depth.load(Ordering::SeqCst),
+                sent: queue.sent.load(Ordering::Relaxed),
+                delayed: queue.delayed.load(Ordering::Relaxed),
+                dropped: queue.dropped.load(Ordering::Relaxed),
+            })
+            .collect();
+        stats.sort_by_key(|stat| stat.self_id);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RateLimitSettings {
+        RateLimitSettings {
+            enabled: true,
+            global_rate: 100.0,
+            global_burst: 10,
+            group_rate: 1.0,
+            group_burst: 1,
+            jitter_ms: 0,
+            max_queue_depth: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn limited_group_does_not_block_other_targets() {
+        let limiter = Arc::new(SendLimiter::new());
+        let settings = settings();
+
+        limiter.acquire(1, Some(100), &settings).await.unwrap();
+
+        // 第二条发往群 100 的消息需要等待约 1 秒的群令牌
+        let waiting = tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            let settings = settings.clone();
+            async move { limiter.acquire(1, Some(100), &settings).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        limiter.acquire(1, Some(200), &settings).await.unwrap();
+        limiter.acquire(1, None, &settings).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+        assert!(!waiting.is_finished());
+
+        waiting.await.unwrap().unwrap();
+        let stats = limiter.stats();
+        assert_eq!(stats[0].sent, 4);
+        assert_eq!(stats[0].delayed, 1);
+        assert_eq!(stats[0].queue_depth, 0);
+    }
+
+    #[tokio::test]
+    async fn full_queue_drops_requests() {
+        let limiter = Arc::new(SendLimiter::new());
+        let settings = RateLimitSettings { max_queue_depth: 1, ..settings() };
+
+        limiter.acquire(1, Some(100), &settings).await.unwrap();
+        let waiting = tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            let settings = settings.clone();
+            async move { limiter.acquire(1, Some(100), &settings).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(limiter.acquire(1, Some(200), &settings).await.is_err());
+        // 其他机器人的队列互不影响
+        assert!(limiter.acquire(2, Some(100), &settings).await.is_ok());
+
+        waiting.abort();
+        let _ = waiting.await;
+        let stats = limiter.stats();
+        assert_eq!(stats[0].dropped, 1);
+        assert_eq!(stats[0].queue_depth, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_does_not_hold_the_bot_queue() {
+        let limiter = Arc::new(SendLimiter::new());
+        let settings = RateLimitSettings { jitter_ms: 1000, ..settings() };
+
+        // 抖动期间不持有全局令牌桶，十个请求的抖动同时进行，总耗时不超过单次抖动上限
+        let started = tokio::time::Instant::now();
+        let tasks: Vec<_> = (0..10).map(|_| tokio::spawn({
+            let limiter = Arc::clone(&limiter);
+            let settings = settings.clone();
+            async move { limiter.acquire(1, None, &settings).await }
+        })).collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert!(started.elapsed() <= Duration::from_millis(1000), "{:?}", started.elapsed());
+        assert_eq!(limiter.stats()[0].sent, 10);
+    }
+
+    #[test]
+    fn rate_limit_is_off_by_default() {
+        assert!(!RateLimitSettings::default().enabled);
+    }
+}
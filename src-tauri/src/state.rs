@@ -0,0 +1,157 @@
+use crate::admin_api::AdminApiHandle;
+use crate::config::{AppConfig, AppSettings, ConfigManager};
+use crate::log_storage::LogStorage;
+use crate::log_store::LogStore;
+use crate::onebot::BotAccount;
+use crate::outbox::Outbox;
+use crate::plugins::PluginSystem;
+use crate::send_queue::SendLimiter;
+use crate::websocket_server::OneBotServer;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock as StdRwLock, Weak};
+use tokio::sync::{Mutex, RwLock};
+
+/// 向前端推送事件的函数，参数为事件名与事件内容
+pub type EventEmitter = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+/// 机器人心跳跟踪信息
+pub struct BotHeartbeat {
+    /// 最近一次收到该机器人事件的时间（使用 tokio 时钟，测试中可以暂停与快进）
+    pub last_seen: tokio::time::Instant,
+    /// 心跳事件声明的间隔（毫秒），未收到心跳前为空
+    pub interval: Option<u64>,
+}
+
+/// 应用核心状态
+///
+/// 每个实例拥有独立的服务器、配置、日志、账号缓存与插件系统，所有文件都位于
+/// 配置目录或显式指定的工作目录中，不依赖进程的当前目录。桌面端通过 `app.manage()` 注册一个实例，测试可以并行创建多个互不影响的实例。
+pub struct AppState {
+    /// 服务器实例注册表（按 ServerConfig::id 索引）
+    pub servers: Mutex<HashMap<String, Arc<OneBotServer>>>,
+    /// 配置管理器，只在读写服务器配置与保存设置时加锁
+    pub config_manager: Mutex<Option<ConfigManager>>,
+    /// 当前设置的快照，热路径读取设置时不需要锁定配置管理器
+    settings: StdRwLock<Arc<AppSettings>>,
+    /// 日志缓冲区与实时日志订阅者
+    pub logs: Arc<LogStore>,
+    /// 日志持久化存储（配置管理器初始化后打开）
+    pub log_storage: Mutex<Option<LogStorage>>,
+    /// 机器人账号缓存
+    pub bot_accounts: Mutex<HashMap<i64, BotAccount>>,
+    /// 机器人心跳跟踪
+    pub bot_heartbeats: Mutex<HashMap<i64, BotHeartbeat>>,
+    /// 最近一次开始获取机器人昵称的时间，获取中或失败后的冷却期内不再重复请求
+    pub nickname_lookups: Mutex<HashMap<i64, tokio::time::Instant>>,
+    /// 发送消息限速器
+    pub send_limiter: SendLimiter,
+    /// 发件箱（配置管理器初始化后加载）
+    pub outbox: Mutex<Option<Outbox>>,
+    /// 工作目录，`config/plugins.toml` 与 `plugins/` 位于其中，未指定时使用配置目录
+    work_dir: StdRwLock<Option<PathBuf>>,
+    /// 插件系统
+    pub plugin_system: RwLock<Option<Arc<PluginSystem>>>,
+    /// 本地管理接口，按设置启动或停止
+    pub admin_api: Mutex<Option<AdminApiHandle>>,
+    /// 前端事件推送函数，未设置时不推送
+    emitter: StdRwLock<Option<EventEmitter>>,
+    /// 指向自身的弱引用，只持有 `&AppState` 的代码通过它启动后台任务
+    me: Weak<AppState>,
+}
+
+impl AppState {
+    pub fn new() -> Arc<Self> {
+        Arc::new_cyclic(|me| Self {
+            servers: Mutex::new(HashMap::new()),
+            config_manager: Mutex::new(None),
+            settings: StdRwLock::new(Arc::new(AppConfig::default().settings)),
+            logs: Arc::new(LogStore::default()),
+            log_storage: Mutex::new(None),
+            bot_accounts: Mutex::new(HashMap::new()),
+            bot_heartbeats: Mutex::new(HashMap::new()),
+            nickname_lookups: Mutex::new(HashMap::new()),
+            send_limiter: SendLimiter::new(),
+            outbox: Mutex::new(None),
+            work_dir: StdRwLock::new(None),
+            plugin_system: RwLock::new(None),
+            admin_api: Mutex::new(None),
+            emitter: StdRwLock::new(None),
+            me: me.clone(),
+        })
+    }
+
+    /// 获取自身的 Arc，实例已在销毁时返回 `None`
+    pub fn arc(&self) -> Option<Arc<AppState>> {
+        self.me.upgrade()
+    }
+
+    /// 获取当前设置的快照
+    pub fn settings(&self) -> Arc<AppSettings> {
+        Arc::clone(&self.settings.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 刷新设置快照，配置管理器中的设置变化后调用
+    fn refresh_settings(&self, settings: &AppSettings) {
+        self.logs.set_max_entries(settings.max_log_entries as usize);
+        *self.settings.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(settings.clone());
+    }
+
+    /// 设置配置管理器并同步设置快照
+    pub async fn set_config_manager(&self, manager: ConfigManager) {
+        let mut config_guard = self.config_manager.lock().await;
+        self.refresh_settings(manager.get_settings());
+        *config_guard = Some(manager);
+    }
+
+    /// 保存设置并同步设置快照
+    pub async fn update_settings(&self, settings: AppSettings) -> Result<(), String> {
+        let mut config_guard = self.config_manager.lock().await;
+        let manager = config_guard.as_mut().ok_or("配置管理器未初始化")?;
+        manager.update_settings(settings).map_err(|e| e.to_string())?;
+        self.refresh_settings(manager.get_settings());
+        Ok(())
+    }
+
+    /// 指定工作目录
+    pub fn set_work_dir(&self, dir: PathBuf) {
+        *self.work_dir.write().unwrap_or_else(|e| e.into_inner()) = Some(dir);
+    }
+
+    /// 尚未指定工作目录时使用 `dir`
+    pub fn init_work_dir(&self, dir: PathBuf) {
+        self.work_dir.write().unwrap_or_else(|e| e.into_inner()).get_or_insert(dir);
+    }
+
+    /// 获取工作目录
+    pub fn work_dir(&self) -> Result<PathBuf, String> {
+        self.work_dir.read().unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| "工作目录未设置，请先初始化配置管理器".to_string())
+    }
+
+    /// 获取插件系统
+    pub async fn plugin_system(&self) -> Option<Arc<PluginSystem>> {
+        self.plugin_system.read().await.clone()
+    }
+
+    /// 设置前端事件推送函数
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn set_emitter(&self, emitter: EventEmitter) {
+        *self.emitter.write().unwrap_or_else(|e| e.into_inner()) = Some(emitter);
+    }
+
+    /// 向前端推送事件
+    pub fn emit<T: Serialize>(&self, event: &str, payload: &T) {
+        let emitter = self.emitter.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let Some(emitter) = emitter else {
+            return;
+        };
+
+        match serde_json::to_value(payload) {
+            Ok(payload) => emitter(event, payload),
+            Err(e) => eprintln!("序列化事件 {} 失败: {}", event, e),
+        }
+    }
+}
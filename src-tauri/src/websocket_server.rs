@@ -1,21 +1,145 @@
-use crate::onebot::{OneBotEvent, ConnectionStatus, OneBotConfig, OneBotApiResponse, OneBotApiRequest, format_event_log};
+use crate::auth;
+use crate::config::{LogEntry, LogLevel};
+use crate::event_bus::{self, EventBus, EventStats};
+use crate::log_store::LogStore;
+use crate::http_post::{self, QuickOperationHandler};
+use crate::onebot::{ConnectionStatus, ConnectionMode, OneBotConfig, OneBotApiResponse, OneBotApiRequest, OneBotVersion, format_event_log};
+use crate::onebot_v12::{self, StringIdMap};
 use futures_util::{SinkExt, StreamExt};
 use serde_json;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::fs::File;
+use std::io::BufReader;
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{mpsc, Mutex, RwLock};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock, Semaphore};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::{accept_hdr_async, connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
+/// 正向 WebSocket 首次重连等待时间
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// 正向 WebSocket 最大重连等待时间
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+/// 反向 WebSocket 的 TLS 握手与 WebSocket 握手各自的最长等待时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 连接在等待响应期间关闭时 API 请求返回的错误
+pub const CONNECTION_CLOSED: &str = "连接已关闭";
+
+/// 连接断开回调，参数为该连接所属的机器人账号
+pub type DisconnectCallback = Arc<dyn Fn(i64) + Send + Sync>;
+
+/// 等待中的 API 请求表
+pub type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<Result<OneBotApiResponse, String>>>>>;
+
+/// 解析 CIDR 列表，单个 IP 视为只包含该地址的网段
+pub fn parse_cidrs(list: &[String]) -> Result<Vec<IpNet>, String> {
+    list.iter()
+        .map(|cidr| {
+            let cidr = cidr.trim();
+            cidr.parse::<IpNet>()
+                .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("无效的 IP 段: {}", cidr))
+        })
+        .collect()
+}
+
+/// 按拒绝列表与允许列表检查来源地址
+pub fn check_address(ip: IpAddr, allowlist: &[IpNet], denylist: &[IpNet]) -> Result<(), String> {
+    // IPv4 映射的 IPv6 地址按 IPv4 匹配
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    };
+
+    if let Some(net) = denylist.iter().find(|net| net.contains(&ip)) {
+        return Err(format!("地址 {} 命中拒绝列表 {}", ip, net));
+    }
+    if !allowlist.is_empty() && !allowlist.iter().any(|net| net.contains(&ip)) {
+        return Err(format!("地址 {} 不在允许列表中", ip));
+    }
+    Ok(())
+}
+
+/// 反向 WebSocket 客户端角色（`X-Client-Role`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientRole {
+    /// 同时收发事件与 API
+    #[default]
+    Universal,
+    /// 仅用于 API 调用
+    Api,
+    /// 仅用于事件上报
+    Event,
+}
+
+impl ClientRole {
+    /// 按 `X-Client-Role` 请求头解析，大小写不敏感
+    fn from_header(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "universal" => Some(Self::Universal),
+            "api" => Some(Self::Api),
+            "event" => Some(Self::Event),
+            _ => None,
+        }
+    }
+
+    /// 按请求路径解析：以 `/api` 或 `/event` 结尾的路径为分离式端点
+    fn from_path(path: &str) -> Option<Self> {
+        let path = path.trim_end_matches('/');
+        if path.ends_with("/api") {
+            Some(Self::Api)
+        } else if path.ends_with("/event") {
+            Some(Self::Event)
+        } else {
+            None
+        }
+    }
+
+    /// 是否可以发送 API 请求
+    pub fn accepts_api(&self) -> bool {
+        !matches!(self, Self::Event)
+    }
+}
+
+/// 握手阶段确定的连接属性
+#[derive(Debug, Clone, Copy, Default)]
+struct Handshake {
+    self_id: Option<i64>,
+    version: OneBotVersion,
+    role: ClientRole,
+}
+
 /// WebSocket 连接信息
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Connection {
     pub id: String,
     pub addr: SocketAddr,
+    /// 握手时 `X-Self-ID` 声明的机器人账号（未声明时由首个事件补全）
+    pub self_id: Option<i64>,
+    /// 连接使用的协议版本（握手子协议或首个事件确定）
+    pub version: OneBotVersion,
+    /// 连接角色
+    pub role: ClientRole,
+    /// 分离式连接中与本连接配对的另一条连接
+    ///
+    /// 同一 self_id 的 API 与 Event 连接配成一对，作为同一个机器人跟踪；
+    /// API 请求优先走已配对的 API 连接，残留的未配对连接只作后备。
+    pub paired_with: Option<String>,
     pub sender: mpsc::UnboundedSender<Message>,
+    /// 等待响应的 API 请求（echo -> 响应通道），连接关闭时立即以错误结束
+    pub pending: PendingRequests,
 }
 
 /// 连接信息（不包含 sender，用于返回）
@@ -26,40 +150,90 @@ pub struct ConnectionInfo {
     pub id: String,
     #[allow(dead_code)]
     pub addr: SocketAddr,
+    pub self_id: Option<i64>,
+    pub version: OneBotVersion,
+    pub role: ClientRole,
+    pub paired_with: Option<String>,
 }
 
-/// OneBot 反向 WebSocket 服务器
+/// 连接处理任务共用的服务器状态
+#[derive(Clone)]
+struct ConnectionContext {
+    connections: Arc<RwLock<HashMap<String, Connection>>>,
+    event_bus: EventBus,
+    disconnect_callback: Arc<Mutex<Option<DisconnectCallback>>>,
+    logs: Arc<LogStore>,
+    string_ids: Arc<StringIdMap>,
+}
+
+/// OneBot WebSocket 服务器（支持反向监听与正向连接两种方式）
 pub struct OneBotServer {
     config: OneBotConfig,
     connections: Arc<RwLock<HashMap<String, Connection>>>,
     status: Arc<Mutex<ConnectionStatus>>,
-    event_callback: Arc<Mutex<Option<fn(OneBotEvent)>>>,
+    event_bus: EventBus,
+    disconnect_callback: Arc<Mutex<Option<DisconnectCallback>>>,
+    quick_operation_handler: Arc<Mutex<Option<QuickOperationHandler>>>,
     shutdown_sender: Arc<Mutex<Option<mpsc::UnboundedSender<()>>>>,
+    logs: Arc<LogStore>,
+    /// v12 字符串 ID 映射，本服务器的所有连接共用
+    string_ids: Arc<StringIdMap>,
+    /// 按类型统计的事件数
+    event_stats: Arc<EventStats>,
 }
 
 impl OneBotServer {
     /// 创建新的 OneBot 服务器实例
-    pub fn new(config: OneBotConfig) -> Self {
+    pub fn new(config: OneBotConfig, logs: Arc<LogStore>) -> Self {
         Self {
             config,
             connections: Arc::new(RwLock::new(HashMap::new())),
             status: Arc::new(Mutex::new(ConnectionStatus::Disconnected)),
-            event_callback: Arc::new(Mutex::new(None)),
+            event_bus: EventBus::new(event_bus::DEFAULT_CAPACITY, Arc::clone(&logs)),
+            disconnect_callback: Arc::new(Mutex::new(None)),
+            quick_operation_handler: Arc::new(Mutex::new(None)),
             shutdown_sender: Arc::new(Mutex::new(None)),
+            logs,
+            string_ids: Arc::new(StringIdMap::default()),
+            event_stats: Arc::new(EventStats::default()),
+        }
+    }
+
+    /// 连接处理任务使用的服务器状态
+    fn connection_context(&self) -> ConnectionContext {
+        ConnectionContext {
+            connections: Arc::clone(&self.connections),
+            event_bus: self.event_bus.clone(),
+            disconnect_callback: Arc::clone(&self.disconnect_callback),
+            logs: Arc::clone(&self.logs),
+            string_ids: Arc::clone(&self.string_ids),
         }
     }
 
-    /// 设置事件回调函数
-    pub async fn set_event_callback(&self, callback: fn(OneBotEvent)) {
-        let mut cb = self.event_callback.lock().await;
+    /// 获取服务器的事件总线，收到的所有事件都会发布到总线上
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    /// 获取服务器的事件统计
+    pub fn event_stats(&self) -> &Arc<EventStats> {
+        &self.event_stats
+    }
+
+    /// 设置连接断开回调函数，参数为该连接所属的机器人账号
+    pub async fn set_disconnect_callback(&self, callback: DisconnectCallback) {
+        let mut cb = self.disconnect_callback.lock().await;
         *cb = Some(callback);
     }
 
+    /// 设置快速操作处理函数（仅 HTTP POST 模式使用）
+    pub async fn set_quick_operation_handler(&self, handler: QuickOperationHandler) {
+        let mut h = self.quick_operation_handler.lock().await;
+        *h = Some(handler);
+    }
+
     /// 启动服务器
     pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let addr = format!("{}:{}", self.config.host, self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
-        
         // 创建shutdown通道
         let (shutdown_tx, mut shutdown_rx) = mpsc::unbounded_channel();
         {
@@ -72,7 +246,53 @@ impl OneBotServer {
             *status = ConnectionStatus::Connecting;
         }
 
-        println!("OneBot 反向 WebSocket 服务器启动于: {}", addr);
+        let result = match self.config.mode {
+            ConnectionMode::ReverseWs => self.run_reverse_server(&mut shutdown_rx).await,
+            ConnectionMode::ForwardWs => self.run_forward_client(&mut shutdown_rx).await,
+            ConnectionMode::HttpPost => self.run_http_post_server(&mut shutdown_rx).await,
+        };
+
+        // 关闭所有连接
+        {
+            let connections = self.connections.read().await;
+            for conn in connections.values() {
+                let _ = conn.sender.send(Message::Close(None));
+            }
+        }
+
+        // 设置状态为已断开
+        {
+            let mut status = self.status.lock().await;
+            *status = ConnectionStatus::Disconnected;
+        }
+
+        println!("OneBot 服务器已停止");
+        result
+    }
+
+    /// 反向 WebSocket：监听端口并接受 OneBot 实现的连接
+    async fn run_reverse_server(
+        &self,
+        shutdown_rx: &mut mpsc::UnboundedReceiver<()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // 配置了证书时先加载，失败直接返回错误，不监听端口
+        let tls_acceptor = match (self.config.tls_cert_path.as_deref(), self.config.tls_key_path.as_deref()) {
+            (Some(cert_path), Some(key_path)) => Some(Self::load_tls_acceptor(cert_path, key_path)?),
+            (None, None) => None,
+            _ => return Err("TLS 证书与私钥路径需要同时配置".into()),
+        };
+
+        let allowlist = parse_cidrs(&self.config.ip_allowlist)?;
+        let denylist = parse_cidrs(&self.config.ip_denylist)?;
+        let max_connections = self.config.max_connections.filter(|max| *max > 0);
+        // 连接名额在接受 TCP 连接时占用，握手失败或连接断开后释放，握手中的连接也计入上限
+        let connection_slots = max_connections.map(|max| Arc::new(Semaphore::new(max as usize)));
+
+        let addr = format!("{}:{}", self.config.host, self.config.port);
+        let listener = TcpListener::bind(&addr).await?;
+
+        let scheme = if tls_acceptor.is_some() { "wss" } else { "ws" };
+        println!("OneBot 反向 WebSocket 服务器启动于: {}://{}", scheme, addr);
 
         {
             let mut status = self.status.lock().await;
@@ -90,13 +310,39 @@ impl OneBotServer {
                 result = listener.accept() => {
                     match result {
                         Ok((stream, addr)) => {
-                            let connections = Arc::clone(&self.connections);
-                            let event_callback = Arc::clone(&self.event_callback);
+                            // 握手前检查来源地址与连接数，不通过时直接断开 TCP 连接
+                            if let Err(reason) = check_address(addr.ip(), &allowlist, &denylist) {
+                                self.log_rejection(addr, &reason);
+                                continue;
+                            }
+                            let permit = match connection_slots.as_ref().map(|slots| Arc::clone(slots).try_acquire_owned()) {
+                                Some(Err(_)) => {
+                                    self.log_rejection(addr, &format!("连接数已达上限 {}", max_connections.unwrap_or_default()));
+                                    continue;
+                                }
+                                Some(Ok(permit)) => Some(permit),
+                                None => None,
+                            };
+
+                            let context = self.connection_context();
                             let access_token = self.config.access_token.clone();
+                            let tls_acceptor = tls_acceptor.clone();
 
                             tokio::spawn(async move {
-                                if let Err(e) = Self::handle_connection(stream, addr, connections, event_callback, access_token).await {
-                                    eprintln!("处理连接时出错: {}", e);
+                                let _permit = permit;
+
+                                // 启用 TLS 时先完成 TLS 握手，再进行 WebSocket 握手
+                                let result = match tls_acceptor {
+                                    Some(acceptor) => match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                                        Ok(Ok(tls_stream)) => Self::handle_connection(tls_stream, addr, context, access_token).await,
+                                        Ok(Err(e)) => Err(format!("TLS 握手失败: {}", e).into()),
+                                        Err(_) => Err("TLS 握手超时".into()),
+                                    },
+                                    None => Self::handle_connection(stream, addr, context, access_token).await,
+                                };
+
+                                if let Err(e) = result {
+                                    eprintln!("处理连接时出错 ({}): {}", addr, e);
                                 }
                             });
                         }
@@ -109,61 +355,373 @@ impl OneBotServer {
             }
         }
 
-        // 关闭所有连接
+        Ok(())
+    }
+
+    /// HTTP POST：监听端口接收 OneBot 实现上报的事件
+    async fn run_http_post_server(
+        &self,
+        shutdown_rx: &mut mpsc::UnboundedReceiver<()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let addr = format!("{}:{}", self.config.host, self.config.port);
+        let listener = TcpListener::bind(&addr).await?;
+
+        println!("OneBot HTTP POST 服务器启动于: http://{}{}", addr, self.config.post_path.as_deref().unwrap_or("/"));
+
         {
-            let connections = self.connections.read().await;
-            for conn in connections.values() {
-                let _ = conn.sender.send(Message::Close(None));
+            let mut status = self.status.lock().await;
+            *status = ConnectionStatus::Connected;
+        }
+
+        // 来源地址与并发上限的限制与反向 WebSocket 一致
+        let rules = http_post::AccessRules {
+            allowlist: parse_cidrs(&self.config.ip_allowlist)?,
+            denylist: parse_cidrs(&self.config.ip_denylist)?,
+            max_connections: self.config.max_connections.filter(|max| *max > 0),
+        };
+        let app = http_post::router(
+            self.config.secret.clone(),
+            self.event_bus.clone(),
+            Arc::clone(&self.logs),
+            Arc::clone(&self.quick_operation_handler),
+            Arc::clone(&self.string_ids),
+            self.config.post_path.as_deref().unwrap_or("/"),
+            rules,
+        );
+
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                println!("收到shutdown信号，停止服务器");
+            }
+            result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()) => {
+                result?;
             }
         }
 
-        // 设置状态为已断开
-        {
-            let mut status = self.status.lock().await;
-            *status = ConnectionStatus::Disconnected;
+        Ok(())
+    }
+
+    /// 正向 WebSocket：主动连接 OneBot 实现，断开后按指数退避重连
+    async fn run_forward_client(
+        &self,
+        shutdown_rx: &mut mpsc::UnboundedReceiver<()>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let url = self.config.url.clone()
+            .filter(|url| !url.trim().is_empty())
+            .ok_or("正向 WebSocket 模式需要配置连接地址")?;
+
+        // 提前校验地址，格式错误时直接返回错误而不是无限重连
+        url.as_str().into_client_request()?;
+
+        println!("OneBot 正向 WebSocket 客户端启动，目标: {}", url);
+
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        loop {
+            {
+                let mut status = self.status.lock().await;
+                *status = ConnectionStatus::Connecting;
+            }
+
+            let mut request = url.as_str().into_client_request()?;
+            if let Some(token) = self.config.access_token.as_deref().filter(|token| !token.is_empty()) {
+                request.headers_mut().insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", token))?);
+            }
+
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    println!("收到shutdown信号，停止正向 WebSocket 客户端");
+                    break;
+                }
+                result = connect_async(request) => {
+                    match result {
+                        Ok((ws_stream, _)) => {
+                            delay = RECONNECT_INITIAL_DELAY;
+                            {
+                                let mut status = self.status.lock().await;
+                                *status = ConnectionStatus::Connected;
+                            }
+
+                            let addr = match ws_stream.get_ref() {
+                                MaybeTlsStream::Plain(stream) => stream.peer_addr().ok(),
+                                MaybeTlsStream::Rustls(stream) => stream.get_ref().0.peer_addr().ok(),
+                                _ => None,
+                            }.unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)));
+
+                            println!("已连接到正向 WebSocket: {} ({})", url, addr);
+
+                            let context = self.connection_context();
+                            tokio::select! {
+                                _ = shutdown_rx.recv() => {
+                                    println!("收到shutdown信号，停止正向 WebSocket 客户端");
+                                    break;
+                                }
+                                _ = Self::serve_connection(ws_stream, addr, Handshake::default(), context) => {
+                                    println!("正向 WebSocket 连接已断开: {}", url);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            // 连接失败的原因（如 TLS 证书不受信任）写入日志，便于在界面中排查
+                            eprintln!("连接正向 WebSocket {} 失败: {}", url, e);
+                            self.logs.add(LogEntry::new(
+                                LogLevel::Warning,
+                                "server".to_string(),
+                                format!("[WARN] 连接正向 WebSocket {} 失败: {}", url, e),
+                                None,
+                            ));
+                        }
+                    }
+                }
+            }
+
+            {
+                let mut status = self.status.lock().await;
+                *status = ConnectionStatus::Connecting;
+            }
+
+            println!("{} 秒后重新连接 {}", delay.as_secs(), url);
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    println!("收到shutdown信号，停止正向 WebSocket 客户端");
+                    break;
+                }
+                _ = tokio::time::sleep(delay) => {}
+            }
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
         }
 
-        println!("OneBot 服务器已停止");
         Ok(())
     }
 
     /// 获取当前连接列表
-    #[allow(dead_code)]
     pub async fn get_connections(&self) -> Vec<ConnectionInfo> {
         let connections = self.connections.read().await;
         connections.values().map(|conn| ConnectionInfo {
             id: conn.id.clone(),
             addr: conn.addr,
+            self_id: conn.self_id,
+            version: conn.version,
+            role: conn.role,
+            paired_with: conn.paired_with.clone(),
         }).collect()
     }
 
-    /// 发送 API 请求到所有连接的客户端
-    pub async fn send_api_request(&self, request: OneBotApiRequest) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let connections = self.connections.read().await;
-        let request_json = serde_json::to_string(&request)?;
+    /// 检查指定机器人是否连接在此服务器上
+    pub async fn has_bot(&self, self_id: i64) -> bool {
+        self.connections.read().await
+            .values()
+            .any(|conn| conn.self_id == Some(self_id))
+    }
+
+    /// 发送 API 请求到指定机器人的连接
+    pub async fn send_api_request(&self, self_id: i64, mut request: OneBotApiRequest, timeout: Duration) -> Result<OneBotApiResponse, String> {
+        let echo = request.echo.get_or_insert_with(|| Uuid::new_v4().to_string()).clone();
 
-        for connection in connections.values() {
-            if let Err(e) = connection.sender.send(tokio_tungstenite::tungstenite::Message::Text(request_json.clone())) {
-                eprintln!("发送 API 请求失败: {}", e);
+        let (sender, pending, version) = {
+            let connections = self.connections.read().await;
+            let bot_connections: Vec<&Connection> = connections.values()
+                .filter(|conn| conn.self_id == Some(self_id))
+                .collect();
+            if bot_connections.is_empty() {
+                return Err(format!("机器人 {} 没有活跃的 OneBot 连接", self_id));
             }
+
+            // 分离式连接中 Event 连接不接收 API 请求，优先使用已配对的 API 连接，其次是未配对的 API 连接
+            let connection = bot_connections.into_iter()
+                .filter(|conn| conn.role.accepts_api())
+                .min_by_key(|conn| (conn.role != ClientRole::Api, conn.paired_with.is_none()))
+                .ok_or_else(|| format!("机器人 {} 没有可用于 API 调用的连接", self_id))?;
+
+            (connection.sender.clone(), Arc::clone(&connection.pending), connection.version)
+        };
+
+        let request_json = match version {
+            OneBotVersion::V11 => serde_json::to_string(&request),
+            OneBotVersion::V12 => serde_json::to_string(&onebot_v12::action_to_v12(&request, self_id, &self.string_ids)),
+        }.map_err(|e| format!("序列化 API 请求失败: {}", e))?;
+
+        // 先登记再发送，避免响应先于登记到达
+        let (tx, rx) = oneshot::channel();
+        pending.lock().await.insert(echo.clone(), tx);
+        // 发送通道已关闭说明连接正在断开，请求没有发出
+        if sender.send(Message::Text(request_json)).is_err() {
+            pending.lock().await.remove(&echo);
+            return Err(CONNECTION_CLOSED.to_string());
         }
 
-        Ok(())
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("API 响应通道关闭".to_string()),
+            Err(_) => {
+                pending.lock().await.remove(&echo);
+                Err(format!("API 请求超时 ({}, {}秒)", request.action, timeout.as_secs()))
+            }
+        }
+    }
+
+    /// 为分离式连接寻找同一机器人尚未配对的另一种角色的连接，找到后互相记录
+    fn pair_connection(connections: &mut HashMap<String, Connection>, connection_id: &str) {
+        let Some(conn) = connections.get(connection_id) else { return };
+        let counterpart_role = match conn.role {
+            ClientRole::Api => ClientRole::Event,
+            ClientRole::Event => ClientRole::Api,
+            ClientRole::Universal => return,
+        };
+        let Some(self_id) = conn.self_id.filter(|_| conn.paired_with.is_none()) else { return };
+
+        let Some(counterpart_id) = connections.values()
+            .find(|other| other.self_id == Some(self_id) && other.role == counterpart_role && other.paired_with.is_none())
+            .map(|other| other.id.clone())
+        else {
+            return;
+        };
+
+        if let Some(conn) = connections.get_mut(connection_id) {
+            conn.paired_with = Some(counterpart_id.clone());
+        }
+        if let Some(counterpart) = connections.get_mut(&counterpart_id) {
+            counterpart.paired_with = Some(connection_id.to_string());
+        }
+        println!("机器人 {} 的分离式连接已配对: {} <-> {}", self_id, connection_id, counterpart_id);
+    }
+
+    /// 移除连接；原配对的连接解除配对后尝试与其他未配对的连接重新配对
+    fn remove_connection(connections: &mut HashMap<String, Connection>, connection_id: &str) {
+        let Some(conn) = connections.remove(connection_id) else { return };
+        if let Some(peer_id) = conn.paired_with {
+            if let Some(peer) = connections.get_mut(&peer_id) {
+                peer.paired_with = None;
+            }
+            Self::pair_connection(connections, &peer_id);
+        }
+    }
+
+    /// 记录被拒绝的连接
+    fn log_rejection(&self, addr: SocketAddr, reason: &str) {
+        println!("拒绝 OneBot 连接 ({}): {}", addr, reason);
+        self.logs.add(LogEntry::new(
+            LogLevel::Warning,
+            "server".to_string(),
+            format!("[WARN] 拒绝 OneBot 连接 ({}): {}", addr, reason),
+            None,
+        ));
+    }
+
+    /// 从 PEM 文件加载证书链与私钥，创建 TLS 接收器
+    fn load_tls_acceptor(cert_path: &str, key_path: &str) -> Result<TlsAcceptor, Box<dyn std::error::Error + Send + Sync>> {
+        let cert_file = File::open(cert_path)
+            .map_err(|e| format!("无法打开证书文件 {}: {}", cert_path, e))?;
+        let certs = rustls_pemfile::certs(&mut BufReader::new(cert_file))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("解析证书文件 {} 失败: {}", cert_path, e))?;
+        if certs.is_empty() {
+            return Err(format!("证书文件 {} 中没有证书", cert_path).into());
+        }
+
+        let key_file = File::open(key_path)
+            .map_err(|e| format!("无法打开私钥文件 {}: {}", key_path, e))?;
+        let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))
+            .map_err(|e| format!("解析私钥文件 {} 失败: {}", key_path, e))?
+            .ok_or_else(|| format!("私钥文件 {} 中没有私钥", key_path))?;
+
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| format!("证书与私钥不匹配: {}", e))?;
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
     }
 
     /// 处理 WebSocket 连接
-    async fn handle_connection(
-        stream: TcpStream,
+    #[allow(clippy::result_large_err)]
+    async fn handle_connection<S>(
+        stream: S,
         addr: SocketAddr,
-        connections: Arc<RwLock<HashMap<String, Connection>>>,
-        event_callback: Arc<Mutex<Option<fn(OneBotEvent)>>>,
+        context: ConnectionContext,
         access_token: Option<String>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let ws_stream = accept_async(stream).await?;
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        // 握手阶段校验访问令牌，失败时直接返回 401，不会注册连接
+        let mut handshake = Handshake::default();
+        let handshake_result = tokio::time::timeout(HANDSHAKE_TIMEOUT, accept_hdr_async(stream, |request: &Request, mut response: Response| {
+            handshake.self_id = request.headers().get("X-Self-ID")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<i64>().ok());
+
+            // 角色以 X-Client-Role 为准，未声明时按路径判断；两者冲突时拒绝连接
+            let header_role = request.headers().get("X-Client-Role")
+                .and_then(|v| v.to_str().ok())
+                .and_then(ClientRole::from_header);
+            let path_role = ClientRole::from_path(request.uri().path());
+            handshake.role = match (header_role, path_role) {
+                (Some(header), Some(path)) if header != path => {
+                    println!("拒绝 OneBot 连接 ({}): X-Client-Role 与路径 {} 不一致", addr, request.uri().path());
+                    return Err(Self::error_response(StatusCode::BAD_REQUEST, "Client role does not match path"));
+                }
+                (Some(role), _) | (None, Some(role)) => role,
+                (None, None) => ClientRole::Universal,
+            };
+
+            // 仅 API 的连接收不到事件，无法补全 self_id，必须在握手时声明
+            if handshake.role == ClientRole::Api && handshake.self_id.is_none() {
+                println!("拒绝 OneBot 连接 ({}): API 连接缺少 X-Self-ID", addr);
+                return Err(Self::error_response(StatusCode::BAD_REQUEST, "Missing X-Self-ID"));
+            }
+
+            // v12 反向 WebSocket 通过 `Sec-WebSocket-Protocol: 12.<实现名>` 声明版本，需原样回应
+            if let Some(protocol) = request.headers().get("Sec-WebSocket-Protocol")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').map(str::trim).find(|p| p.starts_with("12.")))
+            {
+                handshake.version = OneBotVersion::V12;
+                if let Ok(value) = protocol.parse() {
+                    response.headers_mut().insert("Sec-WebSocket-Protocol", value);
+                }
+            }
+
+            match access_token.as_deref() {
+                Some(token) if !token.is_empty() && !auth::verify_access_token(request.headers(), request.uri(), token) => {
+                    println!("拒绝 OneBot 连接 ({}): 访问令牌无效", addr);
+                    Err(Self::error_response(StatusCode::UNAUTHORIZED, "Unauthorized"))
+                }
+                _ => Ok(response),
+            }
+        })).await;
+        let ws_stream = handshake_result.map_err(|_| "WebSocket 握手超时")??;
+
+        Self::serve_connection(ws_stream, addr, handshake, context).await;
+        Ok(())
+    }
+
+    /// 构造握手失败的 HTTP 响应
+    fn error_response(status: StatusCode, message: &str) -> ErrorResponse {
+        let mut error = ErrorResponse::new(Some(message.to_string()));
+        *error.status_mut() = status;
+        error
+    }
+
+    /// 在已建立的 WebSocket 连接上收发消息，直到连接关闭
+    ///
+    /// 反向与正向连接共用此逻辑：事件交给回调处理，API 响应按 echo 回填连接的等待表。
+    /// v12 事件与响应在此转换为 v11 形式，上层只看到统一的事件类型。
+    async fn serve_connection<S>(
+        ws_stream: WebSocketStream<S>,
+        addr: SocketAddr,
+        handshake: Handshake,
+        context: ConnectionContext,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let ConnectionContext { connections, event_bus, disconnect_callback, logs, string_ids } = context;
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        let Handshake { mut self_id, mut version, role } = handshake;
         
         let connection_id = Uuid::new_v4().to_string();
         let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
         
         // 保存连接信息
         {
@@ -171,11 +729,17 @@ impl OneBotServer {
             conns.insert(connection_id.clone(), Connection {
                 id: connection_id.clone(),
                 addr,
+                self_id,
+                version,
+                role,
+                paired_with: None,
                 sender: tx,
+                pending: Arc::clone(&pending),
             });
+            Self::pair_connection(&mut conns, &connection_id);
         }
 
-        println!("新的 OneBot 连接: {} ({})", connection_id, addr);
+        println!("新的 OneBot 连接: {} ({}) self_id: {:?} role: {:?}", connection_id, addr, self_id, role);
 
         // 处理发送消息的任务
         let sender_task = tokio::spawn(async move {
@@ -191,41 +755,57 @@ impl OneBotServer {
         let receiver_task = {
             let connection_id = connection_id.clone();
             let connections = Arc::clone(&connections);
+            let pending = Arc::clone(&pending);
             
             tokio::spawn(async move {
                 while let Some(msg) = ws_receiver.next().await {
                     match msg {
                         Ok(Message::Text(text)) => {
-                            // 验证访问令牌（如果配置了）
-                            if let Some(ref _token) = access_token {
-                                // 这里可以添加更复杂的验证逻辑
-                                // 简单示例：检查消息中是否包含正确的token
-                            }
-                            
                             // 首先尝试解析为 API 响应
-                            if let Ok(api_response) = serde_json::from_str::<OneBotApiResponse>(&text) {
+                            if let Ok(mut api_response) = serde_json::from_str::<OneBotApiResponse>(&text) {
+                                // v12 响应先转换为 v11 字段
+                                if version == OneBotVersion::V12 {
+                                    api_response = onebot_v12::normalize_response(api_response, &string_ids);
+                                }
+
                                 // 这是 API 响应，处理它
                                 if let Some(echo) = &api_response.echo {
                                     // 通知等待的 API 调用
-                                    if let Some(sender) = crate::API_RESPONSE_MAP.lock().await.remove(echo) {
-                                        let _ = sender.send(api_response);
+                                    if let Some(sender) = pending.lock().await.remove(echo) {
+                                        let _ = sender.send(Ok(api_response));
                                     }
                                 }
                             } else {
-                                // 尝试解析为 OneBot 事件
-                                match serde_json::from_str::<OneBotEvent>(&text) {
-                                    Ok(event) => {
+                                // 尝试解析为 OneBot 事件（v12 事件转换为 v11 形式）
+                                match onebot_v12::parse_event(&text, self_id, &string_ids) {
+                                    Ok((event, event_version)) => {
+                                        // 首个 v12 事件确定连接的协议版本
+                                        if event_version != version {
+                                            version = event_version;
+                                            if let Some(conn) = connections.write().await.get_mut(&connection_id) {
+                                                conn.version = version;
+                                            }
+                                        }
+
+                                        // 握手未携带 X-Self-ID 时，用事件中的 self_id 补全
+                                        if self_id.is_none() && event.self_id() != 0 {
+                                            self_id = Some(event.self_id());
+                                            let mut conns = connections.write().await;
+                                            if let Some(conn) = conns.get_mut(&connection_id) {
+                                                conn.self_id = self_id;
+                                            }
+                                            Self::pair_connection(&mut conns, &connection_id);
+                                        }
+
                                         // 使用格式化函数显示友好的日志信息
                                         println!("{}", format_event_log(&event));
 
-                                        // 调用事件回调
-                                        if let Some(callback) = *event_callback.lock().await {
-                                            callback(event);
-                                        }
+                                        // 发布到事件总线
+                                        event_bus.publish(event);
                                     }
                                     Err(e) => {
                                         println!("[ERROR] 无法解析OneBot消息: {}", e);
-                                        println!("[DEBUG] 原始消息: {}", text);
+                                        logs.record_unknown_frame(&addr.to_string(), &text, &e);
                                     }
                                 }
                             }
@@ -243,9 +823,21 @@ impl OneBotServer {
                 }
                 
                 // 清理连接
-                let mut conns = connections.write().await;
-                conns.remove(&connection_id);
+                Self::remove_connection(&mut *connections.write().await, &connection_id);
                 println!("连接 {} 已移除", connection_id);
+
+                // 等待中的 API 请求立即失败，不再等到超时
+                for (_, sender) in pending.lock().await.drain() {
+                    let _ = sender.send(Err(CONNECTION_CLOSED.to_string()));
+                }
+
+                // 通知连接所属机器人已断开
+                if let Some(self_id) = self_id {
+                    let callback = disconnect_callback.lock().await.clone();
+                    if let Some(callback) = callback {
+                        callback(self_id);
+                    }
+                }
             })
         };
 
@@ -254,8 +846,6 @@ impl OneBotServer {
             _ = sender_task => {},
             _ = receiver_task => {},
         }
-
-        Ok(())
     }
 
     /// 停止服务器
@@ -274,6 +864,7 @@ impl OneBotServer {
     }
 
     /// 获取活跃连接数量
+    #[allow(dead_code)]
     pub async fn get_connection_count(&self) -> usize {
         self.connections.read().await.len()
     }
@@ -307,4 +898,327 @@ impl OneBotServer {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::runtime;
+    use crate::state::AppState;
+    use rustls::pki_types::ServerName;
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+
+    /// 取一个当前空闲的本地端口
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    /// 在临时目录中生成 localhost 的自签名证书与私钥
+    fn write_self_signed(dir: &std::path::Path) -> (rcgen::CertifiedKey, String, String) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+        (certified, cert_path.display().to_string(), key_path.display().to_string())
+    }
+
+    fn tls_connector(trusted: Option<&rcgen::CertifiedKey>) -> TlsConnector {
+        let mut roots = rustls::RootCertStore::empty();
+        if let Some(certified) = trusted {
+            roots.add(certified.cert.der().clone()).unwrap();
+        }
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        TlsConnector::from(Arc::new(config))
+    }
+
+    async fn wait_until<F, Fut>(mut condition: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        for _ in 0..100 {
+            if condition().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("等待条件超时");
+    }
+
+    #[tokio::test]
+    async fn wss_handshake_with_trusted_self_signed_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (certified, cert_path, key_path) = write_self_signed(dir.path());
+
+        let port = free_port();
+        let mut config = ServerConfig::new(
+            "tls".to_string(),
+            "tls".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            Some("s3cret".to_string()),
+        ).to_onebot_config();
+        config.tls_cert_path = Some(cert_path);
+        config.tls_key_path = Some(key_path);
+
+        let server = Arc::new(OneBotServer::new(config, Arc::new(LogStore::default())));
+        let task = tokio::spawn({
+            let server = Arc::clone(&server);
+            async move { server.start().await.map_err(|e| e.to_string()) }
+        });
+        wait_until(|| async { matches!(server.get_status().await, ConnectionStatus::Connected) }).await;
+
+        // 不信任该证书的客户端在 TLS 握手阶段失败
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let untrusted = tls_connector(None)
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await;
+        assert!(untrusted.is_err());
+
+        let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let tls_stream = tls_connector(Some(&certified))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+
+        let mut request = format!("wss://localhost:{}/", port).into_client_request().unwrap();
+        request.headers_mut().insert("Authorization", HeaderValue::from_static("Bearer s3cret"));
+        request.headers_mut().insert("X-Self-ID", HeaderValue::from_static("10001"));
+        let (mut ws_stream, response) = tokio_tungstenite::client_async(request, tls_stream).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        wait_until(|| async { server.has_bot(10001).await }).await;
+        assert_eq!(server.get_connection_count().await, 1);
+
+        ws_stream.close(None).await.unwrap();
+        server.shutdown().await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn forward_client_reconnects_and_round_trips_requests() {
+        use tokio_tungstenite::accept_async;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut config = ServerConfig::new(
+            "forward".to_string(),
+            "forward".to_string(),
+            "127.0.0.1".to_string(),
+            0,
+            None,
+        ).to_onebot_config();
+        config.mode = ConnectionMode::ForwardWs;
+        config.url = Some(format!("ws://127.0.0.1:{}/", port));
+
+        let server = Arc::new(OneBotServer::new(config, Arc::new(LogStore::default())));
+        let task = tokio::spawn({
+            let server = Arc::clone(&server);
+            async move { server.start().await.map_err(|e| e.to_string()) }
+        });
+
+        // 模拟的 OneBot 实现：先上报生命周期事件告知 self_id，再回应一次 API 请求后断开
+        for round in 0..2 {
+            let (stream, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await.unwrap().unwrap();
+            let mut ws_stream = accept_async(stream).await.unwrap();
+            let lifecycle = serde_json::json!({
+                "time": 0,
+                "self_id": 10001,
+                "post_type": "meta_event",
+                "meta_event_type": "lifecycle",
+                "sub_type": "connect",
+            });
+            ws_stream.send(Message::Text(lifecycle.to_string())).await.unwrap();
+            wait_until(|| async { server.has_bot(10001).await }).await;
+
+            let request = OneBotApiRequest {
+                action: "get_status".to_string(),
+                params: HashMap::new(),
+                echo: None,
+            };
+            let bot = async {
+                let Some(Ok(Message::Text(text))) = ws_stream.next().await else {
+                    panic!("没有收到 API 请求");
+                };
+                let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(request["action"], "get_status");
+                let response = serde_json::json!({
+                    "status": "ok",
+                    "retcode": 0,
+                    "data": { "round": round },
+                    "echo": request["echo"],
+                });
+                ws_stream.send(Message::Text(response.to_string())).await.unwrap();
+                ws_stream
+            };
+            let (response, ws_stream) = tokio::join!(
+                server.send_api_request(10001, request, Duration::from_secs(5)),
+                bot,
+            );
+            assert_eq!(response.unwrap().data, Some(serde_json::json!({ "round": round })));
+
+            // 服务端断开后客户端应按退避时间重连
+            drop(ws_stream);
+            wait_until(|| async { !server.has_bot(10001).await }).await;
+        }
+
+        server.shutdown().await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn split_api_and_event_connections_are_paired_per_bot() {
+        let port = free_port();
+        let config = ServerConfig::new(
+            "split".to_string(),
+            "split".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            None,
+        ).to_onebot_config();
+
+        let server = Arc::new(OneBotServer::new(config, Arc::new(LogStore::default())));
+        let task = tokio::spawn({
+            let server = Arc::clone(&server);
+            async move { server.start().await.map_err(|e| e.to_string()) }
+        });
+        wait_until(|| async { matches!(server.get_status().await, ConnectionStatus::Connected) }).await;
+
+        let connect = |role: &'static str, self_id: Option<&'static str>| async move {
+            let mut request = format!("ws://127.0.0.1:{}/", port).into_client_request().unwrap();
+            request.headers_mut().insert("X-Client-Role", HeaderValue::from_static(role));
+            if let Some(self_id) = self_id {
+                request.headers_mut().insert("X-Self-ID", HeaderValue::from_static(self_id));
+            }
+            tokio_tungstenite::connect_async(request).await.unwrap().0
+        };
+
+        // API 连接握手带 self_id，Event 连接握手不带，由首个事件补全后配对
+        let mut api = connect("API", Some("10001")).await;
+        let mut event = connect("Event", None).await;
+        let lifecycle = serde_json::json!({
+            "time": 0,
+            "self_id": 10001,
+            "post_type": "meta_event",
+            "meta_event_type": "lifecycle",
+            "sub_type": "connect",
+        });
+        event.send(Message::Text(lifecycle.to_string())).await.unwrap();
+
+        wait_until(|| async {
+            server.get_connections().await.iter().all(|conn| conn.paired_with.is_some())
+        }).await;
+        let connections = server.get_connections().await;
+        assert_eq!(connections.len(), 2);
+        let api_info = connections.iter().find(|conn| conn.role == ClientRole::Api).unwrap();
+        let event_info = connections.iter().find(|conn| conn.role == ClientRole::Event).unwrap();
+        assert_eq!(api_info.paired_with.as_deref(), Some(event_info.id.as_str()));
+        assert_eq!(event_info.paired_with.as_deref(), Some(api_info.id.as_str()));
+        assert!(connections.iter().all(|conn| conn.self_id == Some(10001)));
+
+        // 同一机器人多出的 API 连接不参与配对，请求仍走已配对的连接
+        let mut stale = connect("API", Some("10001")).await;
+        wait_until(|| async { server.get_connection_count().await == 3 }).await;
+
+        let request = OneBotApiRequest {
+            action: "get_status".to_string(),
+            params: HashMap::new(),
+            echo: None,
+        };
+        let bot = async {
+            let Some(Ok(Message::Text(text))) = api.next().await else {
+                panic!("API 连接没有收到请求");
+            };
+            let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let response = serde_json::json!({
+                "status": "ok",
+                "retcode": 0,
+                "data": { "via": "api" },
+                "echo": request["echo"],
+            });
+            api.send(Message::Text(response.to_string())).await.unwrap();
+        };
+        let (response, ()) = tokio::join!(
+            server.send_api_request(10001, request, Duration::from_secs(5)),
+            bot,
+        );
+        assert_eq!(response.unwrap().data, Some(serde_json::json!({ "via": "api" })));
+        let idle = Duration::from_millis(200);
+        assert!(tokio::time::timeout(idle, event.next()).await.is_err());
+        assert!(tokio::time::timeout(idle, stale.next()).await.is_err());
+
+        // Event 连接断开后原 API 连接解除配对，机器人仍由剩余连接跟踪
+        event.close(None).await.unwrap();
+        wait_until(|| async { server.get_connection_count().await == 2 }).await;
+        assert!(server.get_connections().await.iter().all(|conn| conn.paired_with.is_none()));
+        assert!(server.has_bot(10001).await);
+
+        server.shutdown().await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn pending_handshakes_count_towards_connection_limit() {
+        use tokio::io::AsyncReadExt;
+
+        let port = free_port();
+        let mut config = ServerConfig::new(
+            "limit".to_string(),
+            "limit".to_string(),
+            "127.0.0.1".to_string(),
+            port,
+            None,
+        ).to_onebot_config();
+        config.max_connections = Some(1);
+
+        let logs = Arc::new(LogStore::default());
+        let server = Arc::new(OneBotServer::new(config, Arc::clone(&logs)));
+        let task = tokio::spawn({
+            let server = Arc::clone(&server);
+            async move { server.start().await.map_err(|e| e.to_string()) }
+        });
+        wait_until(|| async { matches!(server.get_status().await, ConnectionStatus::Connected) }).await;
+
+        // 第一个连接停在握手前，仍占用唯一的名额
+        let _pending = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut rejected = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(2), rejected.read(&mut buf)).await.unwrap();
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert!(logs.history().iter().any(|entry| entry.content.contains("连接数已达上限 1")));
+        assert_eq!(server.get_connection_count().await, 0);
+
+        server.shutdown().await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn invalid_certificate_path_is_logged_without_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = AppState::new();
+
+        let mut server_config = ServerConfig::new(
+            "bad-tls".to_string(),
+            "bad-tls".to_string(),
+            "127.0.0.1".to_string(),
+            free_port(),
+            None,
+        );
+        server_config.tls_cert_path = Some(dir.path().join("missing.pem").display().to_string());
+        server_config.tls_key_path = Some(dir.path().join("missing.key").display().to_string());
+
+        let error = runtime::start_server_instance(&state, &server_config).await.unwrap_err();
+        assert!(error.contains("无法打开证书文件"), "{}", error);
+
+        let logged = state.logs.history().into_iter()
+            .any(|entry| entry.level == LogLevel::Error && entry.content.contains("无法打开证书文件"));
+        assert!(logged);
+        assert!(state.servers.lock().await.is_empty());
+    }
+}